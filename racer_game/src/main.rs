@@ -1,10 +1,22 @@
+use clap::Parser;
 use macroquad::prelude::*;
 use racer_logic::{
-    controller::{Controller, KeyboardController},
+    car::CarSpec,
+    controller::{
+        Controller, HybridController, KeyboardController, PurePursuitController, StanleyController,
+    },
     environment::Environment,
-    states::{Init, State},
+    follow_camera::{CameraConfig, CameraMode},
+    recorder::Recorder,
+    states::{ControllerChoice, Init, MainMenu, MenuOptions, State, TrackChoice},
+    track::Track,
 };
 use racer_onnx_controller::OnnxController;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 fn window_conf() -> Conf {
     Conf {
@@ -15,24 +27,257 @@ fn window_conf() -> Conf {
     }
 }
 
-fn controller_factory() -> Box<dyn Controller> {
-    if let Some(path) = std::env::args().nth(1) {
-        Box::new(OnnxController::new(&path))
-    } else {
-        Box::new(KeyboardController::default())
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Drive on a generated track, or let a controller drive"
+)]
+struct Args {
+    /// `keyboard`, `ramped-keyboard`, `gamepad`, `pure-pursuit`, `stanley` or the path of an
+    /// ONNX policy. The keyboard can take over from the non-human controllers.
+    #[arg(default_value = "keyboard")]
+    controller: String,
+    /// Directory whose ONNX policies are offered in the main menu.
+    #[arg(long, default_value = "research")]
+    models: PathBuf,
+    /// Directory whose track files are offered in the main menu.
+    #[arg(long, default_value = "tracks")]
+    tracks: PathBuf,
+    /// Runs of the track in one race.
+    #[arg(long, default_value_t = 1)]
+    laps: u32,
+    /// Starts right away with the options given on the command line instead of showing the
+    /// main menu.
+    #[arg(long)]
+    skip_menu: bool,
+    /// Action selection of ONNX policies: `greedy`, `sample`, `sample:<temperature>` or
+    /// `epsilon:<epsilon>`.
+    #[arg(long)]
+    selection: Option<String>,
+    /// Records the driven episodes to this file.
+    #[arg(long)]
+    record: Option<String>,
+    /// Seed of the generated track, random by default.
+    #[arg(long, conflicts_with = "track")]
+    seed: Option<u64>,
+    /// Track file to drive on instead of a generated track.
+    #[arg(long)]
+    track: Option<PathBuf>,
+    /// Handling of the car: `standard`, `sport` or `truck`.
+    #[arg(long, default_value = "standard")]
+    car: CarSpec,
+    /// Camera mode at the start: `chase`, `north-up`, `overview` or `free`. `C` cycles through
+    /// them while driving.
+    #[arg(long, default_value = "chase")]
+    camera: CameraMode,
+    /// Fraction of the distance to the car the camera closes per second, higher is stiffer.
+    #[arg(long, default_value_t = CameraConfig::default().position_rate)]
+    camera_position_rate: f32,
+    /// Fraction of the angle to the car's heading the chase camera turns per second.
+    #[arg(long, default_value_t = CameraConfig::default().rotation_rate)]
+    camera_rotation_rate: f32,
+    /// Prints the seed of the track and the loaded controller.
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+/// Wraps a non-human controller so that the keyboard can take over from it.
+fn with_takeover(controller: impl Controller + 'static) -> Box<dyn Controller> {
+    Box::new(HybridController::new(
+        Box::new(controller),
+        Box::new(KeyboardController::default()),
+    ))
+}
+
+fn load_onnx(path: &str, selection: Option<&str>) -> Result<OnnxController, String> {
+    let controller =
+        OnnxController::new(path).map_err(|e| format!("Failed to load {path}:\n{e}"))?;
+    Ok(match selection {
+        Some(selection) => {
+            controller.with_selection(selection.parse().map_err(|e| format!("{e}"))?, 0)
+        }
+        None => controller,
+    })
+}
+
+fn create_controller(name: &str, selection: Option<&str>) -> Result<Box<dyn Controller>, String> {
+    Ok(match name {
+        "keyboard" => Box::new(KeyboardController::default()),
+        "ramped-keyboard" => Box::new(KeyboardController::ramped(4.0)),
+        #[cfg(feature = "gamepad")]
+        "gamepad" => Box::new(
+            racer_logic::controller::GamepadController::new()
+                .map_err(|e| format!("Failed to initialize gamepad input: {e}"))?,
+        ),
+        "pure-pursuit" => with_takeover(PurePursuitController::default()),
+        "stanley" => with_takeover(StanleyController::default()),
+        path => with_takeover(load_onnx(path, selection)?),
+    })
+}
+
+fn controller_choice(name: String, selection: Option<String>) -> ControllerChoice {
+    let factory_name = name.clone();
+    ControllerChoice {
+        name,
+        factory: Rc::new(move || create_controller(&factory_name, selection.as_deref())),
+    }
+}
+
+/// Files in `directory` with the `extension`, sorted by name. A missing directory has none.
+fn files_with_extension(directory: &Path, extension: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(directory)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|e| e == extension))
+        .collect();
+    files.sort();
+    files
+}
+
+fn menu_options(args: &Args, recorder: Option<Recorder>, camera: CameraConfig) -> MenuOptions {
+    let mut names: Vec<String> = ["keyboard", "ramped-keyboard"]
+        .into_iter()
+        .chain(cfg!(feature = "gamepad").then_some("gamepad"))
+        .chain(["pure-pursuit", "stanley"])
+        .map(String::from)
+        .chain(
+            files_with_extension(&args.models, "onnx")
+                .iter()
+                .map(|path| path.display().to_string()),
+        )
+        .collect();
+    let controller = match names.iter().position(|name| *name == args.controller) {
+        Some(index) => index,
+        None => {
+            names.push(args.controller.clone());
+            names.len() - 1
+        }
+    };
+
+    let mut track_files = files_with_extension(&args.tracks, "track");
+    let track = match (&args.track, args.seed) {
+        (Some(path), _) => {
+            if !track_files.contains(path) {
+                track_files.push(path.clone());
+            }
+            TrackChoice::File(path.clone())
+        }
+        (None, Some(seed)) => TrackChoice::Seed(seed),
+        (None, None) => TrackChoice::Random,
+    };
+    let car = CarSpec::PRESETS
+        .into_iter()
+        .find(|&name| CarSpec::preset(name).as_ref() == Some(&args.car))
+        .unwrap_or(CarSpec::PRESETS[0]);
+
+    MenuOptions {
+        controllers: names
+            .into_iter()
+            .map(|name| controller_choice(name, args.selection.clone()))
+            .collect(),
+        controller,
+        track_files,
+        track,
+        car: car.to_owned(),
+        laps: args.laps,
+        recorder,
+        camera,
     }
 }
 
-#[macroquad::main(window_conf)]
-async fn main() {
-    let mut environment = Environment::new(None);
-    environment.car.load_texture().await;
-    let mut state: Box<dyn State> = Box::new(Init::new(&environment, controller_factory));
+async fn show_error(message: &str) {
+    eprintln!("{message}");
+    while !is_key_pressed(KeyCode::Escape) {
+        clear_background(BLACK);
+        draw_multiline_text(
+            &format!("{message}\n\nPress escape to quit"),
+            5.0,
+            24.0,
+            24.0,
+            None,
+            RED,
+        );
+        next_frame().await;
+    }
+}
 
-    loop {
-        if let Some(next_state) = state.step(&mut environment) {
-            state = next_state;
+fn main() {
+    // parse before opening the window so that `--help` and usage errors reach the terminal
+    let args = Args::parse();
+    macroquad::Window::from_config(window_conf(), run(args));
+}
+
+async fn run(args: Args) {
+    let camera = CameraConfig {
+        mode: args.camera,
+        position_rate: args.camera_position_rate,
+        rotation_rate: args.camera_rotation_rate,
+        ..Default::default()
+    };
+    let recorder = match &args.record {
+        Some(path) => match Recorder::create(path) {
+            Ok(recorder) => Some(recorder),
+            Err(e) => {
+                show_error(&format!("Failed to create {path}:\n{e}")).await;
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let (mut environment, mut state): (_, Box<dyn State>) = if args.skip_menu {
+        let choice = controller_choice(args.controller.clone(), args.selection.clone());
+        let controller = match (choice.factory)() {
+            Ok(controller) => controller,
+            Err(message) => {
+                show_error(&message).await;
+                return;
+            }
+        };
+        let environment = match &args.track {
+            Some(path) => Track::load(path)
+                .map(|track| Environment::with_track(track, 0))
+                .map_err(|e| format!("Failed to load {}:\n{e}", path.display())),
+            None => Ok(Environment::new(args.seed)),
+        };
+        let mut environment = match environment {
+            Ok(environment) => environment.with_car(args.car.clone()),
+            Err(message) => {
+                show_error(&message).await;
+                return;
+            }
+        };
+        if args.verbose {
+            match &args.track {
+                Some(path) => eprintln!("track: {}", path.display()),
+                None => eprintln!("seed: {}", environment.seed),
+            }
+            eprintln!("controller: {}", args.controller);
         }
+        environment.car.load_texture().await;
+        let init = Init::new(&environment, controller, recorder, camera, args.laps);
+        (environment, Box::new(init))
+    } else {
+        let options = menu_options(&args, recorder, camera);
+        if args.verbose {
+            for choice in &options.controllers {
+                eprintln!("controller: {}", choice.name);
+            }
+            for path in &options.track_files {
+                eprintln!("track: {}", path.display());
+            }
+        }
+        // the menu replaces the environment, it only lends the car texture
+        let mut environment = Environment::default();
+        environment.car.load_texture().await;
+        (environment, Box::new(MainMenu::new(options)))
+    };
+
+    // leave the loop on quit so that the state, and the recording with it, is dropped
+    prevent_quit();
+    while !is_quit_requested() {
+        state = state.step(&mut environment);
 
         state.draw(&environment);
 
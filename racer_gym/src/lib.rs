@@ -1,36 +1,155 @@
-use pyo3::prelude::*;
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+use racer_logic::{
+    metrics::{EpisodeMetrics, MetricsLogger, Termination},
+    pixels::{PixelMode, PixelObservation},
+};
 
 #[pyclass(unsendable)]
 struct Environment {
     env: racer_logic::environment::Environment,
+    /// Metrics of the running episode, logged when it finishes or is reset.
+    episode: EpisodeMetrics,
+    logger: Option<MetricsLogger>,
+}
+
+impl Environment {
+    /// Logs the running episode unless it was already logged when it finished.
+    fn log_episode(&mut self, termination: Termination) -> PyResult<()> {
+        let Some(logger) = &mut self.logger else {
+            return Ok(());
+        };
+        if self.episode.steps > 0 && self.episode.termination != Termination::Finished {
+            self.episode.end(&self.env, termination);
+            logger.log(&self.episode)?;
+        }
+        Ok(())
+    }
+
+    /// Features of the observation, or the image around the car when observing pixels.
+    fn observe<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        match (self.env.pixel_observation(), self.env.pixels()) {
+            (Some(config), Some(pixels)) => to_numpy(
+                py,
+                &pixels,
+                (config.height, config.width, config.mode.channels()),
+            ),
+            _ => {
+                let features: Vec<f32> = self.env.observation.clone().into();
+                Ok(features.into_pyobject(py)?.into_any())
+            }
+        }
+    }
+}
+
+/// `bytes` as a uint8 numpy array of `shape`.
+fn to_numpy<'py>(
+    py: Python<'py>,
+    bytes: &[u8],
+    shape: (usize, usize, usize),
+) -> PyResult<Bound<'py, PyAny>> {
+    py.import("numpy")?
+        .call_method1("frombuffer", (PyBytes::new(py, bytes), "uint8"))?
+        .call_method1("reshape", (shape,))?
+        .call_method0("copy")
 }
 
 #[pymethods]
 impl Environment {
+    /// `metrics` is the path of a `.jsonl` or `.csv` file the metrics of every episode are
+    /// appended to.
+    ///
+    /// With `pixels` set to `occupancy` or `rgb`, observations are `(height, width, channels)`
+    /// uint8 numpy arrays of the track around the car, rotated so that the car points up, with
+    /// `scale` pixels per world unit. They are lists of features otherwise.
     #[new]
-    #[pyo3(signature = (seed=0))]
-    pub fn new(seed: Option<u64>) -> Self {
-        Self {
-            env: racer_logic::environment::Environment::new(seed),
+    #[pyo3(signature = (seed=0, metrics=None, pixels=None, width=64, height=64, scale=0.5))]
+    pub fn new(
+        seed: Option<u64>,
+        metrics: Option<&str>,
+        pixels: Option<&str>,
+        width: usize,
+        height: usize,
+        scale: f32,
+    ) -> PyResult<Self> {
+        let mut env = racer_logic::environment::Environment::new(seed);
+        if let Some(mode) = pixels {
+            let mode: PixelMode = mode.parse().map_err(PyValueError::new_err)?;
+            env = env.with_pixels(PixelObservation {
+                width,
+                height,
+                scale,
+                mode,
+            });
         }
+        Ok(Self {
+            episode: EpisodeMetrics::new(0, env.seed),
+            env,
+            logger: metrics.map(MetricsLogger::create).transpose()?,
+        })
     }
 
-    pub fn step(&mut self, steer: f32, throttle: f32) -> (Vec<f32>, f32, bool) {
+    pub fn step<'py>(
+        &mut self,
+        py: Python<'py>,
+        steer: f32,
+        throttle: f32,
+    ) -> PyResult<(Bound<'py, PyAny>, f32, bool)> {
         let action = racer_logic::environment::Action { steer, throttle };
         let outcome = self.env.step(&action, true);
+        self.episode.step(&self.env, &outcome);
+        if outcome.finished {
+            self.log_episode(Termination::Finished)?;
+        }
 
-        let observation: Vec<f32> = self.env.observation.clone().into();
-        (observation, outcome.reward, outcome.finished)
+        Ok((self.observe(py)?, outcome.reward, outcome.finished))
     }
 
-    fn observation(&self) -> Vec<f32> {
-        self.env.observation.clone().into()
+    fn observation<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.observe(py)
+    }
+
+    /// Top-down image centered on the car as a `(height, width, 3)` uint8 numpy array, with
+    /// `scale` pixels per world unit. `rgb_array` is the only mode.
+    #[pyo3(signature = (mode="rgb_array", width=480, height=360, scale=2.0))]
+    fn render<'py>(
+        &self,
+        py: Python<'py>,
+        mode: &str,
+        width: usize,
+        height: usize,
+        scale: f32,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        if mode != "rgb_array" {
+            return Err(PyValueError::new_err(format!(
+                "unsupported render mode '{mode}', expected 'rgb_array'"
+            )));
+        }
+        let raster = self.env.frame(width, height, scale);
+        to_numpy(py, &raster.rgb(), (raster.height(), raster.width(), 3))
     }
 
     #[pyo3(signature = (seed=0))]
-    pub fn reset(&mut self, seed: Option<u64>) -> Vec<f32> {
-        self.env = racer_logic::environment::Environment::new(seed);
-        self.observation()
+    pub fn reset<'py>(
+        &mut self,
+        py: Python<'py>,
+        seed: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        self.log_episode(Termination::Truncated)?;
+        let pixels = self.env.pixel_observation().cloned();
+        let mut env = racer_logic::environment::Environment::new(seed);
+        if let Some(pixels) = pixels {
+            env = env.with_pixels(pixels);
+        }
+        self.env = env;
+        self.episode = EpisodeMetrics::new(self.episode.episode + 1, self.env.seed);
+        self.observe(py)
+    }
+}
+
+impl Drop for Environment {
+    fn drop(&mut self) {
+        // the last episode is logged even when the environment is not reset again
+        self.log_episode(Termination::Truncated).ok();
     }
 }
 
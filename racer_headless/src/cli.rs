@@ -0,0 +1,231 @@
+use crate::{eval::EvalArgs, plot::PlotArgs, render::RenderArgs, train::TrainArgs};
+use clap::{ArgAction, Args, Parser, Subcommand};
+use kdam::{Bar, tqdm};
+use racer_logic::{car::CarSpec, environment::Environment, track::Track};
+use std::{io, path::PathBuf};
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Runs, evaluates and trains racer controllers without a window",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Arguments of `run`, which is the default command.
+    #[command(flatten)]
+    pub run: RunArgs,
+    /// Prints more details, repeat for even more.
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+    /// Prints only the results, without progress bars.
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+impl Cli {
+    pub fn verbosity(&self) -> Verbosity {
+        if self.quiet {
+            Verbosity::Quiet
+        } else {
+            match self.verbose {
+                0 => Verbosity::Normal,
+                1 => Verbosity::Verbose,
+                _ => Verbosity::Debug,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet,
+    #[default]
+    Normal,
+    /// One line per episode.
+    Verbose,
+    /// Rewards of every step.
+    Debug,
+}
+
+impl Verbosity {
+    /// Progress bar over `total` items, hidden when quiet.
+    pub fn progress(self, total: usize) -> Bar {
+        tqdm!(total = total, disable = self == Verbosity::Quiet)
+    }
+
+    /// Ends the line of a finished progress bar.
+    pub fn finish_progress(self) {
+        if self > Verbosity::Quiet {
+            eprintln!();
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Runs a controller over many episodes and counts the finished ones.
+    Run(RunArgs),
+    /// Evaluates a controller on a fixed set of tracks and reports its metrics.
+    Eval(EvalArgs),
+    /// Draws the racing lines of a controller on one track over a heatmap of where it left the
+    /// track, as SVG or PNG.
+    Plot(PlotArgs),
+    /// Records an episode as PNG frames or as an animated GIF.
+    Render(RenderArgs),
+    /// Trains a policy with a policy-gradient method and exports it to ONNX.
+    Train(TrainArgs),
+    /// Saves the track generated from a seed to a track file.
+    Track {
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        output: PathBuf,
+    },
+}
+
+/// Controller driving the car.
+#[derive(Args)]
+pub struct ControllerArgs {
+    /// `pure-pursuit`, `stanley`, `mpc` or the path of an ONNX policy.
+    #[arg(default_value = "research/model.onnx")]
+    pub controller: String,
+    /// Action selection of ONNX policies: `greedy`, `sample`, `sample:<temperature>` or
+    /// `epsilon:<epsilon>`.
+    #[arg(long)]
+    pub selection: Option<String>,
+}
+
+/// Tracks and car the episodes are driven with.
+#[derive(Args)]
+pub struct EpisodeArgs {
+    /// Seeds of the generated tracks, a range `a..b` or a list `a,b,c`.
+    #[arg(long, value_parser = parse_seeds)]
+    pub seeds: Option<Seeds>,
+    /// Track files to drive on, see the `track` command.
+    #[arg(long = "track")]
+    pub tracks: Vec<PathBuf>,
+    /// Handling of the car: `standard`, `sport` or `truck`.
+    #[arg(long, default_value = "standard")]
+    pub car: CarSpec,
+    /// Steps (1/60 s each) after which an unfinished episode is stopped.
+    #[arg(long)]
+    pub max_steps: Option<usize>,
+}
+
+/// Single track and car, of the commands that draw their episodes.
+#[derive(Args)]
+pub struct TrackArgs {
+    /// Seed of the generated track.
+    #[arg(long, default_value_t = 0, conflicts_with = "track")]
+    pub seed: u64,
+    /// Track file to drive on instead of a generated track.
+    #[arg(long)]
+    pub track: Option<PathBuf>,
+    /// Handling of the car: `standard`, `sport` or `truck`.
+    #[arg(long, default_value = "standard")]
+    pub car: CarSpec,
+}
+
+impl TrackArgs {
+    pub fn environment(&self) -> io::Result<Environment> {
+        let environment = match &self.track {
+            Some(path) => {
+                let track = Track::load(path)
+                    .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+                Environment::with_track(track, 0)
+            }
+            None => Environment::new(Some(self.seed)),
+        };
+        Ok(environment.with_car(self.car.clone()))
+    }
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub controller: ControllerArgs,
+    #[command(flatten)]
+    pub episode: EpisodeArgs,
+    /// Number of episodes on random tracks when no seeds or track files are given.
+    #[arg(long, default_value_t = 10_000)]
+    pub episodes: usize,
+    /// Seed of the first random track, the following episodes use the next seeds. Taken from
+    /// the clock when not given.
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Writes the metrics of every episode as JSON Lines (`.jsonl`) or CSV (`.csv`).
+    #[arg(long)]
+    pub metrics: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Seeds(pub Vec<u64>);
+
+fn parse_seeds(value: &str) -> Result<Seeds, String> {
+    let number = |s: &str| s.trim().parse::<u64>().map_err(|e| format!("'{s}': {e}"));
+    match value.split_once("..") {
+        Some((start, end)) => Ok(Seeds((number(start)?..number(end)?).collect())),
+        None => value
+            .split(',')
+            .map(number)
+            .collect::<Result<_, _>>()
+            .map(Seeds),
+    }
+}
+
+/// Track of one episode.
+pub enum Episode {
+    /// Generated from the seed.
+    Seed(u64),
+    /// Loaded from a track file.
+    File(String, Track),
+}
+
+impl Episode {
+    pub fn label(&self) -> String {
+        match self {
+            Episode::Seed(seed) => seed.to_string(),
+            Episode::File(name, _) => name.clone(),
+        }
+    }
+
+    pub fn environment(&self, car: &CarSpec) -> Environment {
+        let environment = match self {
+            Episode::Seed(seed) => Environment::new(Some(*seed)),
+            Episode::File(_, track) => Environment::with_track(track.clone(), 0),
+        };
+        environment.with_car(car.clone())
+    }
+}
+
+impl EpisodeArgs {
+    /// Episodes on the given seeds and track files, `default` when there are none.
+    pub fn episodes(&self, default: impl FnOnce() -> Vec<Episode>) -> io::Result<Vec<Episode>> {
+        let mut episodes: Vec<Episode> = self
+            .seeds
+            .iter()
+            .flat_map(|Seeds(seeds)| seeds.iter().map(|&seed| Episode::Seed(seed)))
+            .collect();
+        for path in &self.tracks {
+            let track = Track::load(path)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+            episodes.push(Episode::File(path.display().to_string(), track));
+        }
+        if episodes.is_empty() {
+            episodes = default();
+        }
+        Ok(episodes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cli;
+    use clap::CommandFactory;
+
+    #[test]
+    fn cli_is_valid() {
+        Cli::command().debug_assert();
+    }
+}
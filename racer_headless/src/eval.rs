@@ -0,0 +1,245 @@
+use crate::cli::{ControllerArgs, Episode, EpisodeArgs, Verbosity};
+use clap::Args;
+use kdam::BarExt;
+use racer_logic::{
+    controller::Controller,
+    environment::Environment,
+    metrics::{EpisodeMetrics, MetricsLogger, Termination},
+};
+use serde::Serialize;
+use std::{fs, io, path::PathBuf};
+
+#[derive(Args)]
+pub struct EvalArgs {
+    #[command(flatten)]
+    pub controller: ControllerArgs,
+    /// Defaults to the seeds `0..100` and 10800 steps (three minutes).
+    #[command(flatten)]
+    episode: EpisodeArgs,
+    /// Writes the summary and the metrics of every episode as JSON.
+    #[arg(long)]
+    json: Option<PathBuf>,
+    /// Writes the metrics of every episode as JSON Lines (`.jsonl`) or CSV (`.csv`), in the
+    /// format of `run`. Existing files are appended to.
+    #[arg(long)]
+    metrics: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+struct Summary {
+    episodes: usize,
+    finish_rate: f32,
+    mean_finish_time: Option<f32>,
+    median_finish_time: Option<f32>,
+    p90_finish_time: Option<f32>,
+    mean_progress: f32,
+    mean_off_track_time: f32,
+    mean_crashes: f32,
+    mean_reward: f32,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    summary: &'a Summary,
+    episodes: &'a [EpisodeMetrics],
+}
+
+fn evaluate(
+    controller: &mut dyn Controller,
+    mut environment: Environment,
+    episode: u64,
+    track: String,
+    max_steps: usize,
+) -> EpisodeMetrics {
+    controller.reset();
+    let mut metrics = EpisodeMetrics::new(episode, environment.seed);
+    metrics.track = track;
+    for _ in 0..max_steps {
+        let action = controller.control(&environment);
+        let outcome = environment.step(&action, true);
+        metrics.step(&environment, &outcome);
+        if outcome.finished {
+            break;
+        }
+    }
+    metrics.end(&environment, Termination::StepLimit);
+    metrics
+}
+
+/// Value below which `fraction` of the sorted `values` lie, interpolated linearly.
+fn percentile(sorted: &[f32], fraction: f32) -> Option<f32> {
+    let position = fraction * (sorted.len().checked_sub(1)? as f32);
+    let (lower, upper) = (position.floor() as usize, position.ceil() as usize);
+    Some(sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as f32))
+}
+
+fn summarize(episodes: &[EpisodeMetrics]) -> Summary {
+    let n = episodes.len().max(1) as f32;
+    let mean = |value: fn(&EpisodeMetrics) -> f32| episodes.iter().map(value).sum::<f32>() / n;
+    let mut finish_times: Vec<f32> = episodes
+        .iter()
+        .filter_map(|episode| episode.finish_time)
+        .collect();
+    finish_times.sort_by(f32::total_cmp);
+
+    Summary {
+        episodes: episodes.len(),
+        finish_rate: finish_times.len() as f32 / n,
+        mean_finish_time: (!finish_times.is_empty())
+            .then(|| finish_times.iter().sum::<f32>() / finish_times.len() as f32),
+        median_finish_time: percentile(&finish_times, 0.5),
+        p90_finish_time: percentile(&finish_times, 0.9),
+        mean_progress: mean(|e| e.progress),
+        mean_off_track_time: mean(|e| e.off_track_time),
+        mean_crashes: mean(|e| e.crashes as f32),
+        mean_reward: mean(|e| e.reward),
+    }
+}
+
+fn print_table(summary: &Summary) {
+    let seconds = |time: Option<f32>| time.map_or("-".to_owned(), |t| format!("{t:.2} s"));
+    let rows = [
+        ("episodes", summary.episodes.to_string()),
+        (
+            "finish rate",
+            format!("{:.1} %", summary.finish_rate * 100.0),
+        ),
+        ("mean finish time", seconds(summary.mean_finish_time)),
+        ("median finish time", seconds(summary.median_finish_time)),
+        ("p90 finish time", seconds(summary.p90_finish_time)),
+        (
+            "mean progress",
+            format!("{:.1} %", summary.mean_progress * 100.0),
+        ),
+        (
+            "mean off-track time",
+            format!("{:.2} s", summary.mean_off_track_time),
+        ),
+        ("mean crashes", format!("{:.2}", summary.mean_crashes)),
+        ("mean reward", format!("{:.1}", summary.mean_reward)),
+    ];
+    for (name, value) in rows {
+        println!("{name:<20} {value:>12}");
+    }
+}
+
+pub fn eval(
+    args: EvalArgs,
+    mut controller: Box<dyn Controller>,
+    verbosity: Verbosity,
+) -> io::Result<()> {
+    let max_steps = args.episode.max_steps.unwrap_or(3 * 60 * 60);
+    let runs = args
+        .episode
+        .episodes(|| (0..100).map(Episode::Seed).collect())?;
+
+    let mut progress = verbosity.progress(runs.len());
+    let mut episodes = vec![];
+    for (episode, run) in (0..).zip(&runs) {
+        let environment = run.environment(&args.episode.car);
+        let metrics = evaluate(
+            controller.as_mut(),
+            environment,
+            episode,
+            run.label(),
+            max_steps,
+        );
+        if verbosity >= Verbosity::Verbose {
+            eprintln!("{metrics:?}");
+        }
+        episodes.push(metrics);
+        progress.update(1).ok();
+    }
+    verbosity.finish_progress();
+
+    let summary = summarize(&episodes);
+    print_table(&summary);
+    if let Some(path) = &args.json {
+        let report = Report {
+            summary: &summary,
+            episodes: &episodes,
+        };
+        fs::write(
+            path,
+            serde_json::to_string_pretty(&report).map_err(io::Error::other)?,
+        )?;
+    }
+    if let Some(path) = &args.metrics {
+        let mut logger = MetricsLogger::create(path)?;
+        for metrics in &episodes {
+            logger.log(metrics)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: Option<f32>, expected: f32) -> bool {
+        actual.is_some_and(|actual| (actual - expected).abs() < 1e-5)
+    }
+
+    #[test]
+    fn percentile_of_nothing() {
+        assert_eq!(percentile(&[], 0.5), None);
+    }
+
+    #[test]
+    fn percentile_of_one_value() {
+        assert_eq!(percentile(&[4.0], 0.0), Some(4.0));
+        assert_eq!(percentile(&[4.0], 0.5), Some(4.0));
+        assert_eq!(percentile(&[4.0], 0.9), Some(4.0));
+    }
+
+    #[test]
+    fn medians() {
+        assert!(close(percentile(&[1.0, 2.0, 10.0], 0.5), 2.0));
+        assert!(close(percentile(&[1.0, 2.0, 4.0, 10.0], 0.5), 3.0));
+    }
+
+    #[test]
+    fn p90_interpolates() {
+        // position 0.9 * 4 = 3.6, between 40 and 50
+        assert!(close(
+            percentile(&[10.0, 20.0, 30.0, 40.0, 50.0], 0.9),
+            46.0
+        ));
+    }
+
+    fn episode(finish_time: Option<f32>, progress: f32) -> EpisodeMetrics {
+        let mut metrics = EpisodeMetrics::new(0, 0);
+        metrics.finished = finish_time.is_some();
+        metrics.finish_time = finish_time;
+        metrics.progress = progress;
+        metrics.reward = 10.0;
+        metrics
+    }
+
+    #[test]
+    fn summary_counts_unfinished_episodes() {
+        let episodes = [
+            episode(Some(30.0), 1.0),
+            episode(None, 0.5),
+            episode(Some(50.0), 1.0),
+            episode(None, 0.1),
+        ];
+        let summary = summarize(&episodes);
+        assert_eq!(summary.episodes, 4);
+        assert!(close(Some(summary.finish_rate), 0.5));
+        assert!(close(summary.mean_finish_time, 40.0));
+        assert!(close(summary.median_finish_time, 40.0));
+        assert!(close(Some(summary.mean_progress), 0.65));
+        assert!(close(Some(summary.mean_reward), 10.0));
+    }
+
+    #[test]
+    fn summary_without_finishes() {
+        let summary = summarize(&[episode(None, 0.2)]);
+        assert_eq!(summary.finish_rate, 0.0);
+        assert_eq!(summary.mean_finish_time, None);
+        assert_eq!(summary.median_finish_time, None);
+        assert_eq!(summary.p90_finish_time, None);
+    }
+}
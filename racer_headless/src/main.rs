@@ -1,36 +1,306 @@
-use kdam::tqdm;
-use racer_logic::{controller::Controller, environment::Environment};
-use racer_onnx_controller::OnnxController;
+use clap::Parser;
+use cli::{Cli, Command, ControllerArgs, Episode, RunArgs, Verbosity};
+use kdam::BarExt;
+use racer_logic::{
+    car::CarSpec,
+    controller::{Controller, PurePursuitController, StanleyController},
+    environment::Environment,
+    metrics::{EpisodeMetrics, MetricsLogger, Termination},
+};
+use racer_mpc_controller::{MpcConfig, MpcController};
+use racer_onnx_controller::{BatchEvaluator, Error, OnnxController};
+use std::{
+    io,
+    process::ExitCode,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-fn main() {
-    let mut controller = OnnxController::new("research/model.onnx");
+mod cli;
+mod eval;
+mod plot;
+mod render;
+mod train;
 
+const MAX_STEPS: usize = 10 * 60;
+/// Number of environments stepped together when evaluating ONNX policies.
+const BATCH_SIZE: usize = 256;
+
+fn load_onnx(path: &str, selection: Option<&str>) -> Result<OnnxController, Error> {
+    // one inference serves a whole batch, let it use all cores
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let controller = OnnxController::load(path, Some(threads))?;
+    Ok(match selection {
+        Some(selection) => controller.with_selection(selection.parse()?, 0),
+        None => controller,
+    })
+}
+
+/// Error of a model that could not be loaded, naming its file.
+fn load_error(path: &str, e: Error) -> io::Error {
+    io::Error::other(format!("Failed to load {path}: {e}"))
+}
+
+fn create_controller(args: &ControllerArgs) -> io::Result<Box<dyn Controller>> {
+    Ok(match args.controller.as_str() {
+        "pure-pursuit" => Box::new(PurePursuitController::default()),
+        "stanley" => Box::new(StanleyController::default()),
+        "mpc" => Box::new(MpcController::new(MpcConfig::default())),
+        path => {
+            Box::new(load_onnx(path, args.selection.as_deref()).map_err(|e| load_error(path, e))?)
+        }
+    })
+}
+
+fn discounted_rewards(rewards: &[f32]) -> Vec<f32> {
     let gamma = 0.99;
-    let mut finish_count = 0;
-    for _ in tqdm!(0..10_000) {
-        let mut env = Environment::new(None);
+    let mut discounted: Vec<f32> = rewards
+        .iter()
+        .rev()
+        .scan(0.0, |acc, r| {
+            *acc = *acc * gamma + r;
+            Some(*acc)
+        })
+        .collect();
+    discounted.reverse();
+    discounted
+}
+
+/// Reports and logs the episodes of `run` as they end.
+struct Report {
+    verbosity: Verbosity,
+    logger: Option<MetricsLogger>,
+    episodes: u64,
+    finished: u64,
+}
+
+impl Report {
+    fn add(&mut self, track: &str, rewards: &[f32], mut metrics: EpisodeMetrics) -> io::Result<()> {
+        metrics.episode = self.episodes;
+        metrics.track = track.to_owned();
+        self.episodes += 1;
+        self.finished += u64::from(metrics.finished);
+
+        let discounted = discounted_rewards(rewards);
+        match self.verbosity {
+            Verbosity::Quiet | Verbosity::Normal => {}
+            Verbosity::Verbose => println!(
+                "track {track}: {}, {} steps, return {:.2}, discounted return {:.2}, progress {:.1} %",
+                if metrics.finished {
+                    "finished"
+                } else {
+                    "not finished"
+                },
+                metrics.steps,
+                metrics.reward,
+                discounted.first().copied().unwrap_or_default(),
+                metrics.progress * 100.0,
+            ),
+            Verbosity::Debug => {
+                if metrics.finished {
+                    print!("Finished!: ");
+                }
+                println!("{discounted:?}");
+            }
+        }
+        match &mut self.logger {
+            Some(logger) => logger.log(&metrics),
+            None => Ok(()),
+        }
+    }
+}
+
+fn run_sequential(
+    mut controller: Box<dyn Controller>,
+    episodes: &[Episode],
+    car: &CarSpec,
+    max_steps: usize,
+    report: &mut Report,
+) -> io::Result<()> {
+    let mut progress = report.verbosity.progress(episodes.len());
+    for episode in episodes {
+        let mut env = episode.environment(car);
+        controller.reset();
         let mut rewards = vec![];
-        for _ in 0..10 * 60 {
-            let action = controller.control(&env.observation);
-            let output = env.step(&action, true);
-            rewards.push(output.reward);
-            if output.finished {
-                print!("Finished!: ");
-                finish_count += 1;
+        let mut metrics = EpisodeMetrics::new(0, env.seed);
+        for _ in 0..max_steps {
+            let action = controller.control(&env);
+            let outcome = env.step(&action, true);
+            rewards.push(outcome.reward);
+            metrics.step(&env, &outcome);
+            if outcome.finished {
                 break;
             }
         }
-        let mut discounted_reward: Vec<f32> = rewards
+        metrics.end(&env, Termination::StepLimit);
+        report.add(&episode.label(), &rewards, metrics)?;
+        progress.update(1).ok();
+    }
+    report.verbosity.finish_progress();
+    Ok(())
+}
+
+/// Builds the environments one batch at a time, so that only a batch of tracks is in memory.
+fn run_batched(
+    controller: OnnxController,
+    episodes: &[Episode],
+    car: &CarSpec,
+    max_steps: usize,
+    report: &mut Report,
+) -> io::Result<()> {
+    let mut evaluator = BatchEvaluator::new(controller, max_steps);
+    let mut progress = report.verbosity.progress(episodes.len());
+    for batch in episodes.chunks(BATCH_SIZE) {
+        let environments = batch
             .iter()
-            .rev()
-            .scan(0.0, |acc, r| {
-                *acc = *acc * gamma + r;
-                Some(*acc)
-            })
+            .map(|episode| episode.environment(car))
             .collect();
-        discounted_reward.reverse();
-        assert_eq!(discounted_reward.len(), rewards.len());
-        println!("{discounted_reward:?}");
+        let results = evaluator
+            .evaluate(environments)
+            .map_err(|e| io::Error::other(format!("Inference failed: {e}")))?;
+        progress.update(results.len()).ok();
+        for (episode, result) in batch.iter().zip(results) {
+            report.add(&episode.label(), &result.rewards, result.metrics)?;
+        }
+    }
+    report.verbosity.finish_progress();
+    Ok(())
+}
+
+fn run(args: RunArgs, verbosity: Verbosity) -> io::Result<()> {
+    let episodes = args.episode.episodes(|| {
+        let first = args.seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_micros() as u64)
+        });
+        (0..args.episodes as u64)
+            .map(|i| Episode::Seed(first.wrapping_add(i)))
+            .collect()
+    })?;
+    let car = &args.episode.car;
+    let max_steps = args.episode.max_steps.unwrap_or(MAX_STEPS);
+    let mut report = Report {
+        verbosity,
+        logger: args
+            .metrics
+            .as_ref()
+            .map(MetricsLogger::create)
+            .transpose()?,
+        episodes: 0,
+        finished: 0,
+    };
+    let controller = &args.controller;
+    match controller.controller.as_str() {
+        "pure-pursuit" | "stanley" | "mpc" => run_sequential(
+            create_controller(controller)?,
+            &episodes,
+            car,
+            max_steps,
+            &mut report,
+        )?,
+        path => run_batched(
+            load_onnx(path, controller.selection.as_deref()).map_err(|e| load_error(path, e))?,
+            &episodes,
+            car,
+            max_steps,
+            &mut report,
+        )?,
+    }
+    eprintln!("Finished episodes: {}/{}", report.finished, report.episodes);
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let verbosity = cli.verbosity();
+    let result = match cli.command {
+        Some(Command::Run(args)) => run(args, verbosity),
+        Some(Command::Eval(args)) => create_controller(&args.controller)
+            .and_then(|controller| eval::eval(args, controller, verbosity)),
+        Some(Command::Plot(args)) => create_controller(&args.controller)
+            .and_then(|controller| plot::plot(args, controller, verbosity)),
+        Some(Command::Render(args)) => create_controller(&args.controller)
+            .and_then(|controller| render::render(args, controller, verbosity)),
+        Some(Command::Train(mut args)) => {
+            args.verbosity = verbosity;
+            train::train(args)
+        }
+        Some(Command::Track { seed, output }) => Environment::new(Some(seed)).track.save(&output),
+        None => run(cli.run, verbosity),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tract"))]
+mod tests {
+    use super::*;
+    use macroquad::rand::RandGenerator;
+    use racer_logic::environment::{OBSERVATION_VERSION, Observation};
+    use racer_onnx_controller::{
+        ActionMapping, DISCRETE_ACTIONS, OutputKind, PolicyKind, PolicyMetadata,
+    };
+    use std::{fs, path::Path};
+
+    /// Runs `run` on a few seeds and returns the CSV metrics it logged.
+    fn metrics(
+        name: &str,
+        model: &Path,
+        run: fn(OnnxController, &[Episode], &CarSpec, usize, &mut Report) -> io::Result<()>,
+    ) -> String {
+        let path = std::env::temp_dir().join(format!("racer-{name}-{}.csv", std::process::id()));
+        let mut report = Report {
+            verbosity: Verbosity::Quiet,
+            logger: Some(MetricsLogger::create(&path).unwrap()),
+            episodes: 0,
+            finished: 0,
+        };
+        let controller = OnnxController::load(model.to_str().unwrap(), Some(1)).unwrap();
+        let episodes: Vec<Episode> = (0..4).map(Episode::Seed).collect();
+        run(controller, &episodes, &CarSpec::default(), 150, &mut report).unwrap();
+        drop(report);
+        let metrics = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        metrics
+    }
+
+    #[test]
+    fn batched_runs_match_sequential_runs() {
+        let features = Observation::feature_names().len();
+        let rng = RandGenerator::new();
+        rng.srand(5);
+        let mlp = train::Mlp::new(&[features, 8, DISCRETE_ACTIONS.len()], &rng);
+        let metadata = PolicyMetadata {
+            input_name: "input".to_owned(),
+            output_name: "output".to_owned(),
+            observation_version: Some(OBSERVATION_VERSION),
+            observation_features: Some(Observation::feature_names()),
+            action_mapping: Some(ActionMapping::Discrete(DISCRETE_ACTIONS.to_vec())),
+            normalization: None,
+            policy: PolicyKind::Greedy,
+            output_kind: OutputKind::Probabilities,
+            frame_stack: 1,
+            recurrent_state: vec![],
+        };
+        let model = std::env::temp_dir().join(format!("racer-run-{}.onnx", std::process::id()));
+        train::export(&mlp, &metadata, &model).unwrap();
+
+        let sequential = metrics(
+            "sequential",
+            &model,
+            |controller, episodes, car, steps, report| {
+                run_sequential(Box::new(controller), episodes, car, steps, report)
+            },
+        );
+        let batched = metrics("batched", &model, run_batched);
+        fs::remove_file(&model).unwrap();
+
+        assert_eq!(sequential.lines().count(), 5);
+        assert_eq!(sequential, batched);
     }
-    eprintln!("Finished episodes: {finish_count}");
 }
@@ -0,0 +1,110 @@
+use crate::cli::{ControllerArgs, TrackArgs, Verbosity};
+use clap::Args;
+use kdam::BarExt;
+use racer_logic::{
+    controller::Controller,
+    environment::Environment,
+    raster::Raster,
+    trajectory::{Heatmap, LineColor, LineQuantity, Trajectory, TrajectoryPlot},
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Args)]
+pub struct PlotArgs {
+    #[command(flatten)]
+    pub controller: ControllerArgs,
+    #[command(flatten)]
+    track: TrackArgs,
+    /// Episodes driven on the track, all their racing lines and off-track events are drawn.
+    #[arg(long, default_value_t = 1)]
+    episodes: usize,
+    /// Steps (1/60 s each) after which an unfinished episode is stopped.
+    #[arg(long, default_value_t = 3 * 60 * 60)]
+    max_steps: usize,
+    /// What the color of the racing line shows: `speed`, `throttle` or `steer`.
+    #[arg(long, default_value = "speed")]
+    color: LineQuantity,
+    /// Side of the heatmap cells in world units.
+    #[arg(long, default_value_t = 10.0)]
+    cell: f32,
+    /// Pixels per world unit of PNG images.
+    #[arg(long, default_value_t = 1.0)]
+    scale: f32,
+    /// Image to write, `.svg` or `.png`.
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+pub fn save_png(raster: &Raster, path: &Path) -> io::Result<()> {
+    image::save_buffer(
+        path,
+        raster.rgba(),
+        raster.width() as u32,
+        raster.height() as u32,
+        image::ColorType::Rgba8,
+    )
+    .map_err(io::Error::other)
+}
+
+fn drive(
+    controller: &mut dyn Controller,
+    environment: &mut Environment,
+    max_steps: usize,
+) -> Trajectory {
+    controller.reset();
+    environment.restart();
+    let mut trajectory = Trajectory::default();
+    for _ in 0..max_steps {
+        let action = controller.control(environment);
+        let outcome = environment.step(&action, true);
+        trajectory.push(environment, &action);
+        if outcome.finished {
+            break;
+        }
+    }
+    trajectory
+}
+
+/// Drives the episodes and draws their racing lines over their off-track heatmap.
+pub fn plot(
+    args: PlotArgs,
+    mut controller: Box<dyn Controller>,
+    verbosity: Verbosity,
+) -> io::Result<()> {
+    let mut environment = args.track.environment()?;
+
+    let mut progress = verbosity.progress(args.episodes);
+    let mut heatmap = Heatmap::new(environment.track.bounds(), args.cell);
+    let mut trajectories = vec![];
+    for _ in 0..args.episodes {
+        let trajectory = drive(controller.as_mut(), &mut environment, args.max_steps);
+        heatmap.add_trajectory(&trajectory);
+        trajectories.push(trajectory);
+        progress.update(1).ok();
+    }
+    verbosity.finish_progress();
+    if verbosity >= Verbosity::Verbose {
+        eprintln!("{} off-track events", heatmap.total());
+    }
+
+    let plot = TrajectoryPlot {
+        track: &environment.track,
+        trajectories: &trajectories,
+        heatmap: &heatmap,
+        line: LineColor {
+            quantity: args.color,
+            top_speed: args.track.car.top_speed(),
+        },
+    };
+    match args.output.extension().and_then(|e| e.to_str()) {
+        Some("svg") => fs::write(&args.output, plot.svg()),
+        Some("png") => save_png(&plot.raster(args.scale), &args.output),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{}: expected a .svg or .png file", args.output.display()),
+        )),
+    }
+}
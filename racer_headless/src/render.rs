@@ -0,0 +1,147 @@
+use crate::{
+    cli::{ControllerArgs, TrackArgs, Verbosity},
+    plot::save_png,
+};
+use clap::Args;
+use image::{
+    Delay, Frame, RgbaImage,
+    codecs::gif::{GifEncoder, Repeat},
+};
+use kdam::BarExt;
+use racer_logic::{controller::Controller, raster::Raster};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Args)]
+pub struct RenderArgs {
+    #[command(flatten)]
+    pub controller: ControllerArgs,
+    #[command(flatten)]
+    track: TrackArgs,
+    /// Steps (1/60 s each) after which an unfinished episode is stopped.
+    #[arg(long, default_value_t = 30 * 60)]
+    max_steps: usize,
+    /// Steps between two frames.
+    #[arg(long, default_value_t = 2)]
+    every: usize,
+    #[arg(long, default_value_t = 480)]
+    width: usize,
+    #[arg(long, default_value_t = 360)]
+    height: usize,
+    /// Pixels per world unit.
+    #[arg(long, default_value_t = 2.0)]
+    scale: f32,
+    /// `.gif` file, or a directory without extension the frames are written to as numbered PNG
+    /// files.
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+/// Where the frames of an episode go.
+enum Sink {
+    Gif(GifEncoder<fs::File>, Delay),
+    Png(PathBuf, usize),
+}
+
+impl Sink {
+    /// Sink for `output`, a `.gif` file or a directory when it has no extension.
+    fn create(output: &Path, every: usize) -> io::Result<Self> {
+        let extension = output
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("gif") => {
+                let mut encoder = GifEncoder::new_with_speed(fs::File::create(output)?, 10);
+                encoder
+                    .set_repeat(Repeat::Infinite)
+                    .map_err(io::Error::other)?;
+                let delay = Delay::from_numer_denom_ms(every as u32 * 1000, 60);
+                Ok(Sink::Gif(encoder, delay))
+            }
+            None => {
+                fs::create_dir_all(output)?;
+                Ok(Sink::Png(output.to_owned(), 0))
+            }
+            Some(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported render output: {}", output.display()),
+            )),
+        }
+    }
+
+    fn add(&mut self, raster: &Raster) -> io::Result<()> {
+        match self {
+            Sink::Gif(encoder, delay) => {
+                let image = RgbaImage::from_raw(
+                    raster.width() as u32,
+                    raster.height() as u32,
+                    raster.rgba().to_vec(),
+                )
+                .expect("the raster holds four bytes per pixel");
+                encoder
+                    .encode_frame(Frame::from_parts(image, 0, 0, *delay))
+                    .map_err(io::Error::other)
+            }
+            Sink::Png(directory, count) => {
+                let path = directory.join(format!("frame_{count:05}.png"));
+                *count += 1;
+                save_png(raster, &path)
+            }
+        }
+    }
+}
+
+/// Drives one episode and writes a frame every few steps.
+pub fn render(
+    args: RenderArgs,
+    mut controller: Box<dyn Controller>,
+    verbosity: Verbosity,
+) -> io::Result<()> {
+    let every = args.every.max(1);
+    let mut sink = Sink::create(&args.output, every)?;
+
+    let mut environment = args.track.environment()?;
+    let mut progress = verbosity.progress(args.max_steps);
+    sink.add(&environment.frame(args.width, args.height, args.scale))?;
+    for step in 1..=args.max_steps {
+        let action = controller.control(&environment);
+        let outcome = environment.step(&action, true);
+        if step % every == 0 || outcome.finished {
+            sink.add(&environment.frame(args.width, args.height, args.scale))?;
+        }
+        progress.update(1).ok();
+        if outcome.finished {
+            break;
+        }
+    }
+    verbosity.finish_progress();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outputs_are_picked_by_extension() {
+        let directory = std::env::temp_dir().join(format!("racer-render-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+
+        let gif = directory.join("out.GIF");
+        assert!(matches!(Sink::create(&gif, 2), Ok(Sink::Gif(..))));
+        assert!(gif.is_file());
+
+        let frames = directory.join("frames");
+        assert!(matches!(Sink::create(&frames, 2), Ok(Sink::Png(..))));
+        assert!(frames.is_dir());
+
+        let png = directory.join("out.png");
+        let error = Sink::create(&png, 2).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(!png.exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
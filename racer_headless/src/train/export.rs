@@ -0,0 +1,305 @@
+//! Writes trained policies as ONNX models loadable by `racer_onnx_controller`.
+//!
+//! Only the subset of the ONNX protobuf schema needed for an MLP is declared here, with the field
+//! numbers of `onnx.proto`.
+
+use super::nn::Mlp;
+use prost::Message;
+use racer_onnx_controller::PolicyMetadata;
+use std::{fs, io, path::Path};
+
+const IR_VERSION: i64 = 8;
+const OPSET_VERSION: i64 = 13;
+const FLOAT: i32 = 1;
+const ATTRIBUTE_INT: i32 = 2;
+
+#[derive(Clone, PartialEq, Message)]
+struct ModelProto {
+    #[prost(int64, tag = "1")]
+    ir_version: i64,
+    #[prost(string, tag = "2")]
+    producer_name: String,
+    #[prost(message, optional, tag = "7")]
+    graph: Option<GraphProto>,
+    #[prost(message, repeated, tag = "8")]
+    opset_import: Vec<OperatorSetIdProto>,
+    #[prost(message, repeated, tag = "14")]
+    metadata_props: Vec<StringStringEntryProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct OperatorSetIdProto {
+    #[prost(string, tag = "1")]
+    domain: String,
+    #[prost(int64, tag = "2")]
+    version: i64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct StringStringEntryProto {
+    #[prost(string, tag = "1")]
+    key: String,
+    #[prost(string, tag = "2")]
+    value: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct GraphProto {
+    #[prost(message, repeated, tag = "1")]
+    node: Vec<NodeProto>,
+    #[prost(string, tag = "2")]
+    name: String,
+    #[prost(message, repeated, tag = "5")]
+    initializer: Vec<TensorProto>,
+    #[prost(message, repeated, tag = "11")]
+    input: Vec<ValueInfoProto>,
+    #[prost(message, repeated, tag = "12")]
+    output: Vec<ValueInfoProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct NodeProto {
+    #[prost(string, repeated, tag = "1")]
+    input: Vec<String>,
+    #[prost(string, repeated, tag = "2")]
+    output: Vec<String>,
+    #[prost(string, tag = "3")]
+    name: String,
+    #[prost(string, tag = "4")]
+    op_type: String,
+    #[prost(message, repeated, tag = "5")]
+    attribute: Vec<AttributeProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct AttributeProto {
+    #[prost(string, tag = "1")]
+    name: String,
+    #[prost(int64, tag = "3")]
+    i: i64,
+    #[prost(int32, tag = "20")]
+    r#type: i32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct TensorProto {
+    #[prost(int64, repeated, tag = "1")]
+    dims: Vec<i64>,
+    #[prost(int32, tag = "2")]
+    data_type: i32,
+    #[prost(float, repeated, tag = "4")]
+    float_data: Vec<f32>,
+    #[prost(string, tag = "8")]
+    name: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ValueInfoProto {
+    #[prost(string, tag = "1")]
+    name: String,
+    #[prost(message, optional, tag = "2")]
+    r#type: Option<TypeProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct TypeProto {
+    #[prost(message, optional, tag = "1")]
+    tensor_type: Option<TensorTypeProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct TensorTypeProto {
+    #[prost(int32, tag = "1")]
+    elem_type: i32,
+    #[prost(message, optional, tag = "2")]
+    shape: Option<TensorShapeProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct TensorShapeProto {
+    #[prost(message, repeated, tag = "1")]
+    dim: Vec<Dimension>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Dimension {
+    #[prost(int64, optional, tag = "1")]
+    dim_value: Option<i64>,
+    #[prost(string, optional, tag = "2")]
+    dim_param: Option<String>,
+}
+
+/// `[batch_size, size]` f32 tensor.
+fn batch_tensor(name: &str, size: usize) -> ValueInfoProto {
+    ValueInfoProto {
+        name: name.to_owned(),
+        r#type: Some(TypeProto {
+            tensor_type: Some(TensorTypeProto {
+                elem_type: FLOAT,
+                shape: Some(TensorShapeProto {
+                    dim: vec![
+                        Dimension {
+                            dim_value: None,
+                            dim_param: Some("batch_size".to_owned()),
+                        },
+                        Dimension {
+                            dim_value: Some(size as i64),
+                            dim_param: None,
+                        },
+                    ],
+                }),
+            }),
+        }),
+    }
+}
+
+fn node(op_type: &str, inputs: &[&str], output: &str, attribute: Vec<AttributeProto>) -> NodeProto {
+    NodeProto {
+        input: inputs.iter().map(|&input| input.to_owned()).collect(),
+        output: vec![output.to_owned()],
+        name: output.to_owned(),
+        op_type: op_type.to_owned(),
+        attribute,
+    }
+}
+
+fn int_attribute(name: &str, value: i64) -> AttributeProto {
+    AttributeProto {
+        name: name.to_owned(),
+        i: value,
+        r#type: ATTRIBUTE_INT,
+    }
+}
+
+/// Exports `mlp` followed by a softmax as an ONNX model with `metadata` in its custom metadata.
+pub fn export(mlp: &Mlp, metadata: &PolicyMetadata, path: impl AsRef<Path>) -> io::Result<()> {
+    let mut nodes = vec![];
+    let mut initializers = vec![];
+    let mut x = metadata.input_name.clone();
+    for (i, layer) in mlp.layers.iter().enumerate() {
+        let weight = format!("layers.{i}.weight");
+        let bias = format!("layers.{i}.bias");
+        initializers.push(TensorProto {
+            dims: vec![layer.outputs as i64, layer.inputs as i64],
+            data_type: FLOAT,
+            float_data: layer.weight.clone(),
+            name: weight.clone(),
+        });
+        initializers.push(TensorProto {
+            dims: vec![layer.outputs as i64],
+            data_type: FLOAT,
+            float_data: layer.bias.clone(),
+            name: bias.clone(),
+        });
+
+        let y = format!("layers.{i}.gemm");
+        nodes.push(node(
+            "Gemm",
+            &[&x, &weight, &bias],
+            &y,
+            vec![int_attribute("transB", 1)],
+        ));
+        x = y;
+        if i + 1 < mlp.layers.len() {
+            let y = format!("layers.{i}.relu");
+            nodes.push(node("Relu", &[&x], &y, vec![]));
+            x = y;
+        }
+    }
+    nodes.push(node(
+        "Softmax",
+        &[&x],
+        &metadata.output_name,
+        vec![int_attribute("axis", -1)],
+    ));
+
+    let inputs = mlp.layers.first().map_or(0, |layer| layer.inputs);
+    let outputs = mlp.layers.last().map_or(0, |layer| layer.outputs);
+    let model = ModelProto {
+        ir_version: IR_VERSION,
+        producer_name: "racer_headless".to_owned(),
+        graph: Some(GraphProto {
+            node: nodes,
+            name: "policy".to_owned(),
+            initializer: initializers,
+            input: vec![batch_tensor(&metadata.input_name, inputs)],
+            output: vec![batch_tensor(&metadata.output_name, outputs)],
+        }),
+        opset_import: vec![OperatorSetIdProto {
+            domain: String::new(),
+            version: OPSET_VERSION,
+        }],
+        metadata_props: metadata
+            .entries()
+            .into_iter()
+            .map(|(key, value)| StringStringEntryProto { key, value })
+            .collect(),
+    };
+    fs::write(path, model.encode_to_vec())
+}
+
+#[cfg(all(test, feature = "tract"))]
+mod tests {
+    use super::*;
+    use crate::train::nn::softmax;
+    use macroquad::rand::RandGenerator;
+    use racer_logic::environment::{Action, Environment, OBSERVATION_VERSION, Observation};
+    use racer_onnx_controller::{
+        ActionMapping, DISCRETE_ACTIONS, Normalization, OnnxController, OutputKind, PolicyKind,
+        PolicyState,
+    };
+
+    #[test]
+    fn exported_model_computes_the_mlp() {
+        let features = Observation::feature_names().len();
+        let rng = RandGenerator::new();
+        rng.srand(7);
+        let mlp = Mlp::new(&[features, 6, DISCRETE_ACTIONS.len()], &rng);
+        let normalization = Normalization {
+            scale: (0..features).map(|i| 0.5 + i as f32 * 0.01).collect(),
+            bias: (0..features).map(|i| i as f32 * -0.02).collect(),
+        };
+        let metadata = PolicyMetadata {
+            input_name: "input".to_owned(),
+            output_name: "output".to_owned(),
+            observation_version: Some(OBSERVATION_VERSION),
+            observation_features: Some(Observation::feature_names()),
+            action_mapping: Some(ActionMapping::Discrete(DISCRETE_ACTIONS.to_vec())),
+            normalization: Some(normalization.clone()),
+            policy: PolicyKind::Stochastic,
+            output_kind: OutputKind::Probabilities,
+            frame_stack: 1,
+            recurrent_state: vec![],
+        };
+        let path = std::env::temp_dir().join(format!("racer-export-{}.onnx", std::process::id()));
+        export(&mlp, &metadata, &path).unwrap();
+        let controller = OnnxController::load(path.to_str().unwrap(), Some(1));
+        fs::remove_file(&path).unwrap();
+        let mut controller = controller.unwrap();
+
+        let mut environment = Environment::new(Some(3));
+        for step in 0..3 {
+            let mut x: Vec<f32> = environment.observation.clone().into();
+            normalization.apply(&mut x);
+            let expected = softmax(&mlp.output(&x));
+            let output = controller
+                .infer_batch(&[&environment.observation], &mut [PolicyState::default()])
+                .unwrap()
+                .remove(0);
+            assert_eq!(output.len(), expected.len());
+            for (o, e) in output.iter().zip(&expected) {
+                assert!(
+                    (o - e).abs() < 1e-5,
+                    "step {step}: {output:?} != {expected:?}"
+                );
+            }
+            let action = Action {
+                steer: 0.3,
+                throttle: 1.0,
+            };
+            for _ in 0..20 {
+                environment.step(&action, true);
+            }
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Policy-gradient training of discrete MLP policies without the Python stack, the
+//! counterpart of `research/train_reinforce.py`.
+
+mod export;
+mod nn;
+mod ppo;
+mod reinforce;
+
+#[cfg(all(test, feature = "tract"))]
+pub(crate) use export::export;
+pub(crate) use nn::Mlp;
+
+use crate::cli::Verbosity;
+use clap::{Args, ValueEnum};
+use macroquad::rand::RandGenerator;
+use nn::Adam;
+use racer_logic::{
+    controller::{Controller, PurePursuitController},
+    environment::{Environment, OBSERVATION_VERSION, Observation},
+    metrics::{EpisodeMetrics, MetricsLogger},
+};
+use racer_onnx_controller::{
+    ActionMapping, DISCRETE_ACTIONS, Normalization, OutputKind, PolicyKind, PolicyMetadata,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const DEFAULT_HIDDEN: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum Algorithm {
+    Reinforce,
+    Ppo,
+}
+
+#[derive(Debug, Args)]
+pub struct TrainArgs {
+    /// Has to be the algorithm of the checkpoint when resuming.
+    #[arg(long, value_enum, default_value_t = Algorithm::Reinforce)]
+    algorithm: Algorithm,
+    /// Number of episodes to train for.
+    #[arg(long, default_value_t = 10_000)]
+    episodes: u64,
+    /// Size of the hidden layer of the policy and value networks, 32 unless resuming, when it
+    /// has to be the size of the checkpoint if given.
+    #[arg(long)]
+    hidden: Option<usize>,
+    /// Also replaces the learning rate of the checkpoint when resuming.
+    #[arg(long, default_value_t = 1e-3)]
+    learning_rate: f32,
+    /// Discount factor of the rewards.
+    #[arg(long, default_value_t = 0.99)]
+    gamma: f32,
+    /// Steps after which an episode is cut short.
+    #[arg(long, default_value_t = 60 * 60)]
+    max_steps: usize,
+    /// Seeds the network initialization, the action sampling and the tracks, episode `i` is
+    /// driven on the track of seed `seed + i`.
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+    /// Episodes driven by the pure pursuit controller to fit the observation normalization.
+    #[arg(long, default_value_t = 10)]
+    warmup_episodes: u64,
+    /// Directory the checkpoints and exported policies are written to.
+    #[arg(long, default_value = "policy-train")]
+    output: PathBuf,
+    /// Episodes between checkpoints.
+    #[arg(long, default_value_t = 100)]
+    checkpoint_every: u64,
+    /// Checkpoint to continue training from.
+    #[arg(long)]
+    resume: Option<PathBuf>,
+    /// Appends the metrics of every episode as JSON Lines (`.jsonl`) or CSV (`.csv`).
+    #[arg(long)]
+    metrics: Option<PathBuf>,
+    /// Set from the global `--verbose` and `--quiet` flags.
+    #[arg(skip)]
+    pub verbosity: Verbosity,
+    /// PPO: steps collected before each update.
+    #[arg(long, default_value_t = 2048)]
+    rollout_steps: usize,
+    /// PPO: passes over each rollout.
+    #[arg(long, default_value_t = 4)]
+    ppo_epochs: usize,
+    /// PPO: steps per gradient update.
+    #[arg(long, default_value_t = 64)]
+    minibatch: usize,
+    /// PPO: range the probability ratio is clipped to.
+    #[arg(long, default_value_t = 0.2)]
+    clip: f32,
+    /// PPO: lambda of the generalized advantage estimation.
+    #[arg(long, default_value_t = 0.95)]
+    gae_lambda: f32,
+    /// PPO: weight of the entropy bonus.
+    #[arg(long, default_value_t = 0.01)]
+    entropy: f32,
+}
+
+/// Everything needed to continue training, saved as JSON checkpoints.
+#[derive(Serialize, Deserialize)]
+struct TrainState {
+    algorithm: Algorithm,
+    episode: u64,
+    running_reward: f32,
+    normalization: Normalization,
+    policy: Mlp,
+    policy_optimizer: Adam,
+    /// Critic of PPO.
+    value: Option<(Mlp, Adam)>,
+    #[serde(skip)]
+    metrics: Option<MetricsLogger>,
+}
+
+impl TrainState {
+    fn new(args: &TrainArgs, rng: &RandGenerator) -> Self {
+        let inputs = Observation::feature_names().len();
+        let hidden = args.hidden.unwrap_or(DEFAULT_HIDDEN);
+        let policy = Mlp::new(&[inputs, hidden, DISCRETE_ACTIONS.len()], rng);
+        let value = (args.algorithm == Algorithm::Ppo).then(|| {
+            let value = Mlp::new(&[inputs, hidden, 1], rng);
+            let optimizer = Adam::new(value.parameter_count(), args.learning_rate);
+            (value, optimizer)
+        });
+        Self {
+            algorithm: args.algorithm,
+            episode: 0,
+            running_reward: 10.0,
+            normalization: fit_normalization(args),
+            policy_optimizer: Adam::new(policy.parameter_count(), args.learning_rate),
+            policy,
+            value,
+            metrics: None,
+        }
+    }
+
+    fn load(path: &Path) -> io::Result<Self> {
+        serde_json::from_str(&fs::read_to_string(path)?).map_err(io::Error::other)
+    }
+
+    /// Continues a loaded checkpoint with the learning rate of `args`, fails when `args` ask for
+    /// another algorithm or network than the checkpoint has.
+    fn resume(&mut self, args: &TrainArgs) -> io::Result<()> {
+        let conflict = |message: String| Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+        if self.algorithm != args.algorithm {
+            return conflict(format!(
+                "the checkpoint was trained with {:?}, not {:?}",
+                self.algorithm, args.algorithm
+            ));
+        }
+        let hidden = self.policy.layers.first().map_or(0, |layer| layer.outputs);
+        if let Some(requested) = args.hidden
+            && requested != hidden
+        {
+            return conflict(format!(
+                "the checkpoint has {hidden} hidden units, not {requested}"
+            ));
+        }
+
+        self.policy_optimizer.learning_rate = args.learning_rate;
+        if let Some((_, optimizer)) = &mut self.value {
+            optimizer.learning_rate = args.learning_rate;
+        }
+        Ok(())
+    }
+
+    /// Normalized observation vector fed to the networks.
+    fn observe(&self, environment: &Environment) -> Vec<f32> {
+        let mut x: Vec<f32> = environment.observation.clone().into();
+        self.normalization.apply(&mut x);
+        x
+    }
+
+    fn metadata(&self) -> PolicyMetadata {
+        PolicyMetadata {
+            input_name: "input".to_owned(),
+            output_name: "output".to_owned(),
+            observation_version: Some(OBSERVATION_VERSION),
+            observation_features: Some(Observation::feature_names()),
+            action_mapping: Some(ActionMapping::Discrete(DISCRETE_ACTIONS.to_vec())),
+            normalization: Some(self.normalization.clone()),
+            policy: PolicyKind::Stochastic,
+            output_kind: OutputKind::Probabilities,
+            frame_stack: 1,
+            recurrent_state: vec![],
+        }
+    }
+
+    /// Logs the metrics of a finished episode and writes a checkpoint every
+    /// `checkpoint_every` episodes.
+    fn finish_episode(&mut self, metrics: &EpisodeMetrics, args: &TrainArgs) -> io::Result<()> {
+        if let Some(logger) = &mut self.metrics {
+            logger.log(metrics)?;
+        }
+        let reward = metrics.reward;
+        self.episode += 1;
+        self.running_reward = 0.05 * reward + (1.0 - 0.05) * self.running_reward;
+        if args.verbosity > Verbosity::Quiet {
+            println!("{},{reward:.2},{:.2}", self.episode, self.running_reward);
+        }
+
+        if self.episode.is_multiple_of(args.checkpoint_every.max(1)) {
+            self.checkpoint(&args.output)?;
+        }
+        Ok(())
+    }
+
+    fn checkpoint(&self, directory: &Path) -> io::Result<()> {
+        fs::create_dir_all(directory)?;
+        let name = format!("{:?}-ep{:05}", self.algorithm, self.episode).to_lowercase();
+        let json = serde_json::to_string(self).map_err(io::Error::other)?;
+        fs::write(directory.join(format!("{name}.json")), json)?;
+        export::export(
+            &self.policy,
+            &self.metadata(),
+            directory.join(format!("{name}.onnx")),
+        )
+    }
+}
+
+/// Min-max scaling of the observations to `[-1, 1]`, like the `MinMaxScaler` of the Python
+/// training, fitted on episodes driven by the pure pursuit controller.
+fn fit_normalization(args: &TrainArgs) -> Normalization {
+    let size = Observation::feature_names().len();
+    let mut min = vec![f32::INFINITY; size];
+    let mut max = vec![f32::NEG_INFINITY; size];
+    for i in 0..args.warmup_episodes.max(1) {
+        let mut environment = Environment::new(Some(args.seed.wrapping_sub(i + 1)));
+        let mut controller = PurePursuitController::default();
+        for _ in 0..args.max_steps {
+            let x: Vec<f32> = environment.observation.clone().into();
+            for ((min, max), x) in min.iter_mut().zip(&mut max).zip(x) {
+                *min = min.min(x);
+                *max = max.max(x);
+            }
+            let action = controller.control(&environment);
+            if environment.step(&action, true).finished {
+                break;
+            }
+        }
+    }
+
+    let (scale, bias) = min
+        .iter()
+        .zip(&max)
+        .map(|(&min, &max)| {
+            if max - min > 1e-6 {
+                let scale = 2.0 / (max - min);
+                (scale, -min * scale - 1.0)
+            } else {
+                // features that never changed are only centered
+                (1.0, -min)
+            }
+        })
+        .unzip();
+    Normalization { scale, bias }
+}
+
+pub fn train(args: TrainArgs) -> io::Result<()> {
+    let rng = RandGenerator::new();
+    rng.srand(args.seed);
+    let mut state = match &args.resume {
+        Some(path) => {
+            let mut state = TrainState::load(path)?;
+            state.resume(&args)?;
+            state
+        }
+        None => TrainState::new(&args, &rng),
+    };
+    state.metrics = args
+        .metrics
+        .as_ref()
+        .map(MetricsLogger::create)
+        .transpose()?;
+    match args.algorithm {
+        Algorithm::Reinforce => reinforce::train(&mut state, &args)?,
+        Algorithm::Ppo => ppo::train(&mut state, &args, &rng)?,
+    }
+    state.checkpoint(&args.output)
+}
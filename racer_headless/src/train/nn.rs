@@ -0,0 +1,279 @@
+use macroquad::rand::RandGenerator;
+use serde::{Deserialize, Serialize};
+
+/// Fully connected layer, `weight` is stored row-major as `[outputs, inputs]` like the weights
+/// of `torch.nn.Linear`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Linear {
+    pub inputs: usize,
+    pub outputs: usize,
+    pub weight: Vec<f32>,
+    pub bias: Vec<f32>,
+}
+
+impl Linear {
+    /// Initialized uniformly in `±1/sqrt(inputs)`, the default of `torch.nn.Linear`.
+    fn new(inputs: usize, outputs: usize, rng: &RandGenerator) -> Self {
+        let bound = 1.0 / (inputs as f32).sqrt();
+        let uniform = || rng.gen_range(-bound, bound);
+        Self {
+            inputs,
+            outputs,
+            weight: (0..inputs * outputs).map(|_| uniform()).collect(),
+            bias: (0..outputs).map(|_| uniform()).collect(),
+        }
+    }
+
+    fn forward(&self, x: &[f32]) -> Vec<f32> {
+        self.weight
+            .chunks(self.inputs)
+            .zip(&self.bias)
+            .map(|(row, bias)| row.iter().zip(x).map(|(w, x)| w * x).sum::<f32>() + bias)
+            .collect()
+    }
+}
+
+/// Multi-layer perceptron with ReLU activations between the layers and a linear output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mlp {
+    pub layers: Vec<Linear>,
+}
+
+/// Inputs of every layer saved by [`Mlp::forward`] for [`Mlp::backward`].
+pub struct Activations(Vec<Vec<f32>>);
+
+impl Mlp {
+    /// `sizes` lists the input size, the hidden sizes and the output size.
+    pub fn new(sizes: &[usize], rng: &RandGenerator) -> Self {
+        Self {
+            layers: sizes
+                .windows(2)
+                .map(|pair| Linear::new(pair[0], pair[1], rng))
+                .collect(),
+        }
+    }
+
+    pub fn forward(&self, x: &[f32]) -> (Vec<f32>, Activations) {
+        let mut activations = vec![x.to_vec()];
+        let mut y = x.to_vec();
+        for (i, layer) in self.layers.iter().enumerate() {
+            y = layer.forward(&y);
+            if i + 1 < self.layers.len() {
+                y.iter_mut().for_each(|v| *v = v.max(0.0));
+                activations.push(y.clone());
+            }
+        }
+        (y, Activations(activations))
+    }
+
+    pub fn output(&self, x: &[f32]) -> Vec<f32> {
+        self.forward(x).0
+    }
+
+    pub fn parameter_count(&self) -> usize {
+        self.layers
+            .iter()
+            .map(|layer| layer.weight.len() + layer.bias.len())
+            .sum()
+    }
+
+    /// All parameters in the order of the gradients accumulated by [`Mlp::backward`].
+    pub fn parameters_mut(&mut self) -> impl Iterator<Item = &mut f32> {
+        self.layers
+            .iter_mut()
+            .flat_map(|layer| layer.weight.iter_mut().chain(layer.bias.iter_mut()))
+    }
+
+    /// Adds the gradient of the parameters given the gradient of the output to `gradient`.
+    pub fn backward(
+        &self,
+        activations: &Activations,
+        output_gradient: &[f32],
+        gradient: &mut [f32],
+    ) {
+        let mut offsets = vec![];
+        let mut offset = 0;
+        for layer in &self.layers {
+            offsets.push(offset);
+            offset += layer.weight.len() + layer.bias.len();
+        }
+
+        let mut delta = output_gradient.to_vec();
+        for (i, layer) in self.layers.iter().enumerate().rev() {
+            let input = &activations.0[i];
+            let (weight_gradient, bias_gradient) =
+                gradient[offsets[i]..].split_at_mut(layer.weight.len());
+            for (o, d) in delta.iter().enumerate() {
+                let row = &mut weight_gradient[o * layer.inputs..(o + 1) * layer.inputs];
+                row.iter_mut().zip(input).for_each(|(g, x)| *g += d * x);
+                bias_gradient[o] += d;
+            }
+            if i == 0 {
+                break;
+            }
+
+            // propagate through the weights and the ReLU of the previous layer
+            let mut previous = vec![0.0; layer.inputs];
+            for (row, d) in layer.weight.chunks(layer.inputs).zip(&delta) {
+                previous.iter_mut().zip(row).for_each(|(p, w)| *p += w * d);
+            }
+            for (p, x) in previous.iter_mut().zip(input) {
+                if *x <= 0.0 {
+                    *p = 0.0;
+                }
+            }
+            delta = previous;
+        }
+    }
+}
+
+pub fn softmax(logits: &[f32]) -> Vec<f32> {
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let exp: Vec<f32> = logits.iter().map(|l| (l - max).exp()).collect();
+    let total: f32 = exp.iter().sum();
+    exp.iter().map(|e| e / total).collect()
+}
+
+/// Adam optimizer over the parameters of one [`Mlp`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Adam {
+    pub learning_rate: f32,
+    beta1: f32,
+    beta2: f32,
+    epsilon: f32,
+    step: i32,
+    m: Vec<f32>,
+    v: Vec<f32>,
+}
+
+impl Adam {
+    pub fn new(parameter_count: usize, learning_rate: f32) -> Self {
+        Self {
+            learning_rate,
+            beta1: 0.9,
+            beta2: 0.999,
+            epsilon: 1e-8,
+            step: 0,
+            m: vec![0.0; parameter_count],
+            v: vec![0.0; parameter_count],
+        }
+    }
+
+    /// Moves the parameters of `mlp` against `gradient`.
+    pub fn step(&mut self, mlp: &mut Mlp, gradient: &[f32]) {
+        self.step += 1;
+        let correction1 = 1.0 - self.beta1.powi(self.step);
+        let correction2 = 1.0 - self.beta2.powi(self.step);
+        for (((p, g), m), v) in mlp
+            .parameters_mut()
+            .zip(gradient)
+            .zip(&mut self.m)
+            .zip(&mut self.v)
+        {
+            *m = self.beta1 * *m + (1.0 - self.beta1) * g;
+            *v = self.beta2 * *v + (1.0 - self.beta2) * g * g;
+            let m_hat = *m / correction1;
+            let v_hat = *v / correction2;
+            *p -= self.learning_rate * m_hat / (v_hat.sqrt() + self.epsilon);
+        }
+    }
+}
+
+/// Scales `gradient` down so that its norm is at most `max_norm`.
+pub fn clip_norm(gradient: &mut [f32], max_norm: f32) {
+    let norm = gradient.iter().map(|g| g * g).sum::<f32>().sqrt();
+    if norm > max_norm {
+        gradient.iter_mut().for_each(|g| *g *= max_norm / norm);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &[f32], expected: &[f32], tolerance: f32) {
+        assert_eq!(actual.len(), expected.len());
+        for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+            assert!(
+                (a - e).abs() <= tolerance * e.abs().max(1.0),
+                "element {i}: {a} != {e}"
+            );
+        }
+    }
+
+    #[test]
+    fn backward_matches_finite_differences() {
+        let rng = RandGenerator::new();
+        rng.srand(1);
+        let mut mlp = Mlp::new(&[3, 5, 4, 2], &rng);
+        let x = [0.3, -0.7, 0.9];
+        // gradient of the loss `0.5 * y0 - 2.0 * y1`
+        let weights = [0.5, -2.0];
+        let loss = |mlp: &Mlp| {
+            let y = mlp.output(&x);
+            y.iter().zip(weights).map(|(y, w)| y * w).sum::<f32>()
+        };
+
+        let (_, activations) = mlp.forward(&x);
+        let mut gradient = vec![0.0; mlp.parameter_count()];
+        mlp.backward(&activations, &weights, &mut gradient);
+
+        let epsilon = 1e-2;
+        let mut numerical = vec![];
+        for i in 0..mlp.parameter_count() {
+            let original = *mlp.parameters_mut().nth(i).unwrap();
+            *mlp.parameters_mut().nth(i).unwrap() = original + epsilon;
+            let plus = loss(&mlp);
+            *mlp.parameters_mut().nth(i).unwrap() = original - epsilon;
+            let minus = loss(&mlp);
+            *mlp.parameters_mut().nth(i).unwrap() = original;
+            numerical.push((plus - minus) / (2.0 * epsilon));
+        }
+        assert_close(&gradient, &numerical, 1e-2);
+    }
+
+    #[test]
+    fn backward_accumulates() {
+        let rng = RandGenerator::new();
+        rng.srand(2);
+        let mlp = Mlp::new(&[2, 3, 2], &rng);
+        let (_, activations) = mlp.forward(&[1.0, -1.0]);
+        let mut once = vec![0.0; mlp.parameter_count()];
+        mlp.backward(&activations, &[1.0, 1.0], &mut once);
+        let mut twice = vec![0.0; mlp.parameter_count()];
+        mlp.backward(&activations, &[1.0, 1.0], &mut twice);
+        mlp.backward(&activations, &[1.0, 1.0], &mut twice);
+        let doubled: Vec<f32> = once.iter().map(|g| 2.0 * g).collect();
+        assert_close(&twice, &doubled, 1e-6);
+    }
+
+    #[test]
+    fn softmax_known_values() {
+        let probabilities = softmax(&[0.0, 2f32.ln(), 3f32.ln()]);
+        assert_close(&probabilities, &[1.0 / 6.0, 2.0 / 6.0, 3.0 / 6.0], 1e-6);
+        // shifted by the maximum, large logits do not overflow
+        assert_close(&softmax(&[1000.0, 1000.0]), &[0.5, 0.5], 1e-6);
+    }
+
+    #[test]
+    fn clip_norm_scales_only_long_gradients() {
+        let mut gradient = [3.0, 4.0];
+        clip_norm(&mut gradient, 10.0);
+        assert_eq!(gradient, [3.0, 4.0]);
+        clip_norm(&mut gradient, 1.0);
+        assert_close(&gradient, &[0.6, 0.8], 1e-6);
+    }
+
+    #[test]
+    fn adam_first_step_moves_by_the_learning_rate() {
+        let rng = RandGenerator::new();
+        rng.srand(3);
+        let mut mlp = Mlp::new(&[1, 1], &rng);
+        let before: Vec<f32> = mlp.parameters_mut().map(|p| *p).collect();
+        let mut adam = Adam::new(mlp.parameter_count(), 0.1);
+        adam.step(&mut mlp, &[2.0, -0.5]);
+        let after: Vec<f32> = mlp.parameters_mut().map(|p| *p).collect();
+        // the bias corrected first step is the learning rate against the sign of the gradient
+        assert_close(&after, &[before[0] - 0.1, before[1] + 0.1], 1e-5);
+    }
+}
@@ -0,0 +1,208 @@
+use super::{
+    TrainArgs, TrainState,
+    nn::{clip_norm, softmax},
+};
+use macroquad::rand::RandGenerator;
+use racer_logic::{
+    environment::{Action, Environment},
+    metrics::{EpisodeMetrics, Termination},
+};
+use racer_onnx_controller::{ActionSelector, DISCRETE_ACTIONS, OutputKind, Selection};
+use std::io;
+
+const VALUE_COEFFICIENT: f32 = 0.5;
+const MAX_GRADIENT_NORM: f32 = 0.5;
+
+/// How the episode continued after a step.
+enum End {
+    Continue,
+    /// The car crossed the finish line.
+    Finished,
+    /// The episode was cut short, its return is estimated by the value of the next observation.
+    Truncated(f32),
+}
+
+struct Transition {
+    observation: Vec<f32>,
+    action: usize,
+    log_prob: f32,
+    value: f32,
+    reward: f32,
+    end: End,
+}
+
+/// Proximal policy optimization with a clipped surrogate objective, generalized advantage
+/// estimation and a separate value network.
+pub fn train(state: &mut TrainState, args: &TrainArgs, rng: &RandGenerator) -> io::Result<()> {
+    let selector = ActionSelector::new(
+        Selection::Sample { temperature: 1.0 },
+        OutputKind::Probabilities,
+        args.seed.wrapping_add(state.episode),
+    );
+    let new_episode = |episode: u64| {
+        let seed = args.seed.wrapping_add(episode);
+        (
+            Environment::new(Some(seed)),
+            EpisodeMetrics::new(episode, seed),
+        )
+    };
+    let (mut environment, mut metrics) = new_episode(state.episode);
+
+    while state.episode < args.episodes {
+        let mut rollout = vec![];
+        for _ in 0..args.rollout_steps {
+            let observation = state.observe(&environment);
+            let probs = softmax(&state.policy.output(&observation));
+            let action = selector.select(&probs);
+            let (steer, throttle) = DISCRETE_ACTIONS[action];
+            let outcome = environment.step(&Action { steer, throttle }, true);
+            metrics.step(&environment, &outcome);
+
+            let end = if outcome.finished {
+                End::Finished
+            } else if metrics.steps >= args.max_steps as u64 {
+                End::Truncated(value(state, &state.observe(&environment)))
+            } else {
+                End::Continue
+            };
+            let episode_over = !matches!(end, End::Continue);
+            rollout.push(Transition {
+                value: value(state, &observation),
+                log_prob: probs[action].max(f32::MIN_POSITIVE).ln(),
+                observation,
+                action,
+                reward: outcome.reward,
+                end,
+            });
+
+            if episode_over {
+                metrics.end(&environment, Termination::StepLimit);
+                state.finish_episode(&metrics, args)?;
+                (environment, metrics) = new_episode(state.episode);
+                if state.episode >= args.episodes {
+                    break;
+                }
+            }
+        }
+
+        let bootstrap = value(state, &state.observe(&environment));
+        let (advantages, returns) = advantages(&rollout, bootstrap, args);
+        update(state, &rollout, &advantages, &returns, args, rng);
+    }
+    Ok(())
+}
+
+fn value(state: &TrainState, observation: &[f32]) -> f32 {
+    let (value, _) = state.value.as_ref().expect("PPO trains a value network");
+    value.output(observation)[0]
+}
+
+/// Generalized advantage estimates and the value targets of the rollout, `bootstrap` is the
+/// value of the observation after the last transition.
+fn advantages(rollout: &[Transition], bootstrap: f32, args: &TrainArgs) -> (Vec<f32>, Vec<f32>) {
+    let mut advantages = vec![0.0; rollout.len()];
+    let mut gae = 0.0;
+    let mut next_value = bootstrap;
+    for (t, transition) in rollout.iter().enumerate().rev() {
+        let (successor_value, continues) = match transition.end {
+            End::Continue => (next_value, true),
+            End::Finished => (0.0, false),
+            End::Truncated(value) => (value, false),
+        };
+        let delta = transition.reward + args.gamma * successor_value - transition.value;
+        gae = delta
+            + if continues {
+                args.gamma * args.gae_lambda * gae
+            } else {
+                0.0
+            };
+        advantages[t] = gae;
+        next_value = transition.value;
+    }
+    let returns = advantages
+        .iter()
+        .zip(rollout)
+        .map(|(advantage, transition)| advantage + transition.value)
+        .collect();
+    (advantages, returns)
+}
+
+fn update(
+    state: &mut TrainState,
+    rollout: &[Transition],
+    advantages: &[f32],
+    returns: &[f32],
+    args: &TrainArgs,
+    rng: &RandGenerator,
+) {
+    let n = advantages.len() as f32;
+    let mean = advantages.iter().sum::<f32>() / n;
+    let std = (advantages.iter().map(|a| (a - mean).powi(2)).sum::<f32>() / n).sqrt();
+    let advantages: Vec<f32> = advantages
+        .iter()
+        .map(|a| (a - mean) / (std + 1e-8))
+        .collect();
+
+    let mut indices: Vec<usize> = (0..rollout.len()).collect();
+    for _ in 0..args.ppo_epochs {
+        // Fisher-Yates shuffle
+        for i in (1..indices.len()).rev() {
+            indices.swap(i, rng.gen_range(0, i + 1));
+        }
+
+        for minibatch in indices.chunks(args.minibatch.max(1)) {
+            let (value_network, value_optimizer) =
+                state.value.as_mut().expect("PPO trains a value network");
+            let mut policy_gradient = vec![0.0; state.policy.parameter_count()];
+            let mut value_gradient = vec![0.0; value_network.parameter_count()];
+            let scale = 1.0 / minibatch.len() as f32;
+
+            for &i in minibatch {
+                let transition = &rollout[i];
+                let advantage = advantages[i];
+                let (logits, activations) = state.policy.forward(&transition.observation);
+                let probs = softmax(&logits);
+
+                // clipped surrogate, the gradient vanishes where the clipped term is the minimum
+                let ratio = (probs[transition.action].max(f32::MIN_POSITIVE).ln()
+                    - transition.log_prob)
+                    .exp();
+                let clipped = (advantage > 0.0 && ratio > 1.0 + args.clip)
+                    || (advantage < 0.0 && ratio < 1.0 - args.clip);
+                let entropy: f32 = -probs
+                    .iter()
+                    .map(|p| p * p.max(f32::MIN_POSITIVE).ln())
+                    .sum::<f32>();
+                let logits_gradient: Vec<f32> = probs
+                    .iter()
+                    .enumerate()
+                    .map(|(j, &p)| {
+                        let surrogate = if clipped {
+                            0.0
+                        } else {
+                            let onehot = if j == transition.action { 1.0 } else { 0.0 };
+                            ratio * advantage * (p - onehot)
+                        };
+                        let entropy_bonus =
+                            args.entropy * p * (p.max(f32::MIN_POSITIVE).ln() + entropy);
+                        (surrogate + entropy_bonus) * scale
+                    })
+                    .collect();
+                state
+                    .policy
+                    .backward(&activations, &logits_gradient, &mut policy_gradient);
+
+                let (value, activations) = value_network.forward(&transition.observation);
+                let value_error = VALUE_COEFFICIENT * (value[0] - returns[i]) * scale;
+                value_network.backward(&activations, &[value_error], &mut value_gradient);
+            }
+
+            clip_norm(&mut policy_gradient, MAX_GRADIENT_NORM);
+            clip_norm(&mut value_gradient, MAX_GRADIENT_NORM);
+            state
+                .policy_optimizer
+                .step(&mut state.policy, &policy_gradient);
+            value_optimizer.step(value_network, &value_gradient);
+        }
+    }
+}
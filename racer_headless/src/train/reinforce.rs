@@ -0,0 +1,75 @@
+use super::{
+    TrainArgs, TrainState,
+    nn::{Activations, softmax},
+};
+use racer_logic::{
+    environment::{Action, Environment},
+    metrics::{EpisodeMetrics, Termination},
+};
+use racer_onnx_controller::{ActionSelector, DISCRETE_ACTIONS, OutputKind, Selection};
+use std::io;
+
+/// REINFORCE with returns normalized per episode, like `research/train_reinforce.py`.
+pub fn train(state: &mut TrainState, args: &TrainArgs) -> io::Result<()> {
+    let selector = ActionSelector::new(
+        Selection::Sample { temperature: 1.0 },
+        OutputKind::Probabilities,
+        args.seed.wrapping_add(state.episode),
+    );
+
+    while state.episode < args.episodes {
+        let seed = args.seed.wrapping_add(state.episode);
+        let mut environment = Environment::new(Some(seed));
+        let mut metrics = EpisodeMetrics::new(state.episode, seed);
+        let mut steps: Vec<(Activations, Vec<f32>, usize)> = vec![];
+        let mut rewards = vec![];
+        for _ in 0..args.max_steps {
+            let (logits, activations) = state.policy.forward(&state.observe(&environment));
+            let probs = softmax(&logits);
+            let action = selector.select(&probs);
+            let (steer, throttle) = DISCRETE_ACTIONS[action];
+            let outcome = environment.step(&Action { steer, throttle }, true);
+            steps.push((activations, probs, action));
+            rewards.push(outcome.reward);
+            metrics.step(&environment, &outcome);
+            if outcome.finished {
+                break;
+            }
+        }
+        metrics.end(&environment, Termination::StepLimit);
+
+        let returns = normalized_returns(&rewards, args.gamma);
+        let mut gradient = vec![0.0; state.policy.parameter_count()];
+        for ((activations, probs, action), ret) in steps.iter().zip(returns) {
+            // gradient of -log(p[action]) * return with respect to the logits
+            let mut logits_gradient: Vec<f32> = probs.iter().map(|p| p * ret).collect();
+            logits_gradient[*action] -= ret;
+            state
+                .policy
+                .backward(activations, &logits_gradient, &mut gradient);
+        }
+        state.policy_optimizer.step(&mut state.policy, &gradient);
+
+        state.finish_episode(&metrics, args)?;
+    }
+    Ok(())
+}
+
+/// Discounted returns standardized to zero mean and unit variance.
+fn normalized_returns(rewards: &[f32], gamma: f32) -> Vec<f32> {
+    let mut returns: Vec<f32> = rewards
+        .iter()
+        .rev()
+        .scan(0.0, |acc, r| {
+            *acc = *acc * gamma + r;
+            Some(*acc)
+        })
+        .collect();
+    returns.reverse();
+
+    let n = returns.len() as f32;
+    let mean = returns.iter().sum::<f32>() / n;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f32>() / (n - 1.0).max(1.0);
+    let std = variance.sqrt();
+    returns.iter().map(|r| (r - mean) / (std + 1e-8)).collect()
+}
@@ -1,9 +1,94 @@
 use macroquad::prelude::*;
-use std::f32::consts::{FRAC_PI_2, FRAC_PI_6};
+use std::{
+    f32::consts::{FRAC_PI_2, FRAC_PI_4, FRAC_PI_6, FRAC_PI_8},
+    str::FromStr,
+};
 
-use crate::{physics::RotRect, track::Track};
+use crate::{physics::RotRect, raster::Raster, track::Track};
 
+/// Steering limits of the standard car.
+pub const MAX_STEERING_ANGLE: f32 = FRAC_PI_6;
+/// Rate at which the steering angle of the standard car changes at full steering input, in
+/// radians per second.
+pub const STEERING_SPEED: f32 = FRAC_PI_6;
+
+/// Color of the car when drawn without its texture.
+const BODY_COLOR: Color = Color::new(0.8, 0.1, 0.1, 1.0);
+
+/// Handling of a car, see [`CarSpec::PRESETS`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CarSpec {
+    /// Change of velocity per second at full throttle.
+    pub acceleration: f32,
+    /// Fraction of the velocity kept every step.
+    pub friction: f32,
+    /// Additional fraction of the velocity kept every step for each wheel off the track.
+    pub off_track_friction: f32,
+    pub max_steering_angle: f32,
+    /// Rate at which the steering angle changes at full steering input, in radians per second.
+    pub steering_speed: f32,
+}
+
+impl CarSpec {
+    pub const PRESETS: [&str; 3] = ["standard", "sport", "truck"];
+
+    /// Velocity reached on the track at full throttle, where acceleration and friction cancel
+    /// out.
+    pub fn top_speed(&self) -> f32 {
+        let dt = 1.0 / 60.0;
+        self.acceleration * dt * self.friction / (1.0 - self.friction)
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "standard" => Some(Self::default()),
+            "sport" => Some(Self {
+                acceleration: 80.0,
+                friction: 0.996,
+                off_track_friction: 0.98,
+                max_steering_angle: FRAC_PI_6,
+                steering_speed: FRAC_PI_4,
+            }),
+            "truck" => Some(Self {
+                acceleration: 30.0,
+                friction: 0.993,
+                off_track_friction: 0.99,
+                max_steering_angle: FRAC_PI_8,
+                steering_speed: FRAC_PI_8,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl Default for CarSpec {
+    fn default() -> Self {
+        Self {
+            acceleration: 50.0,
+            friction: 0.995,
+            off_track_friction: 0.99,
+            max_steering_angle: MAX_STEERING_ANGLE,
+            steering_speed: STEERING_SPEED,
+        }
+    }
+}
+
+impl FromStr for CarSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::preset(s).ok_or_else(|| {
+            format!(
+                "unknown car '{s}', expected one of: {}",
+                Self::PRESETS.join(", ")
+            )
+        })
+    }
+}
+
+#[derive(Clone)]
 pub struct Car {
+    spec: CarSpec,
     texture: Option<Texture2D>,
     position: Vec2,
     rotation: f32,
@@ -16,10 +101,15 @@ pub struct Car {
 
 impl Car {
     pub fn new(x: f32, y: f32) -> Self {
+        Self::with_spec(x, y, CarSpec::default())
+    }
+
+    pub fn with_spec(x: f32, y: f32, spec: CarSpec) -> Self {
         let wheel_base = 14.0;
         let position = vec2(x, y);
         let rotation = FRAC_PI_2;
         Self {
+            spec,
             texture: None,
             position,
             rotation,
@@ -40,6 +130,19 @@ impl Car {
         }
     }
 
+    /// Standing car at `x`, `y` with the spec and texture of this one.
+    pub fn respawned(&self, x: f32, y: f32) -> Self {
+        Self {
+            texture: self.texture.clone(),
+            ..Self::with_spec(x, y, self.spec.clone())
+        }
+    }
+
+    /// Draws the car with the texture loaded by `other`.
+    pub fn share_texture(&mut self, other: &Car) {
+        self.texture = other.texture.clone();
+    }
+
     pub async fn load_texture(&mut self) {
         self.texture = Some(load_texture("assets/car.png").await.unwrap());
     }
@@ -63,23 +166,23 @@ impl Car {
         } else {
             get_frame_time()
         };
-        let turn_speed = FRAC_PI_6;
-
-        self.steering_angle += steer * turn_speed * dt;
+        let spec = &self.spec;
+        self.steering_angle += steer * spec.steering_speed * dt;
         if steer == 0.0 {
             self.steering_angle = self.steering_angle.lerp(0.0, (10.0 * dt).clamp(0.0, 1.0));
         }
-        self.steering_angle = self.steering_angle.clamp(-FRAC_PI_6, FRAC_PI_6);
+        self.steering_angle = self
+            .steering_angle
+            .clamp(-spec.max_steering_angle, spec.max_steering_angle);
 
-        let acceleration = 50.0;
-        self.velocity += throttle * acceleration * dt;
+        self.velocity += throttle * spec.acceleration * dt;
 
         let penalty = wheels_on_track
             .iter()
             .filter(|&&on_track| !on_track)
-            .map(|_| 0.99)
+            .map(|_| spec.off_track_friction)
             .product::<f32>();
-        let friction = 0.995 * penalty;
+        let friction = spec.friction * penalty;
         self.velocity *= friction;
 
         let pos_dot = Vec2::from_angle(self.rotation) * self.velocity;
@@ -134,15 +237,30 @@ impl Car {
         }
     }
 
-    pub fn wheels_on_track(&self, track: &Track) -> [bool; 4] {
-        let orientation = Vec2::from_angle(self.rotation - FRAC_PI_2);
-        let mut ans = [false; 4];
-        for (i, wheel) in self.wheels.iter().enumerate() {
-            let pos = self.position + orientation.rotate(*wheel);
-            let on_track = track.on_track(&pos);
-            ans[i] = on_track;
+    /// Draws the wheels and a plain body in place of the texture, without a window.
+    pub fn raster(&self, raster: &mut Raster) {
+        let draw_rot = self.rotation - FRAC_PI_2;
+        for (i, wheel_pos) in self.wheel_positions().into_iter().enumerate() {
+            let mut wheel_rot = draw_rot;
+            if i < 2 {
+                wheel_rot += self.steering_angle;
+            }
+            raster.fill_rotated_rect(wheel_pos, vec2(1.5, 3.0), wheel_rot, BLACK);
         }
-        ans
+        raster.fill_polygon(&self.bbox.outline(), BODY_COLOR);
+        let windshield = self.position_with_offset(self.wheel_base);
+        raster.fill_rotated_rect(windshield, vec2(7.0, 3.0), draw_rot, SKYBLUE);
+    }
+
+    /// Positions of the front right, front left, rear right and rear left wheel.
+    pub fn wheel_positions(&self) -> [Vec2; 4] {
+        let orientation = Vec2::from_angle(self.rotation - FRAC_PI_2);
+        self.wheels
+            .map(|wheel| self.position + orientation.rotate(wheel))
+    }
+
+    pub fn wheels_on_track(&self, track: &Track) -> [bool; 4] {
+        self.wheel_positions().map(|pos| track.on_track(&pos))
     }
 
     pub fn bbox(&self) -> &RotRect {
@@ -185,4 +303,12 @@ impl Car {
     pub fn steering_angle(&self) -> &f32 {
         &self.steering_angle
     }
+
+    pub fn wheel_base(&self) -> f32 {
+        self.wheel_base
+    }
+
+    pub fn spec(&self) -> &CarSpec {
+        &self.spec
+    }
 }
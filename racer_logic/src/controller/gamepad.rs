@@ -0,0 +1,88 @@
+use crate::{
+    controller::Controller,
+    environment::{Action, Environment},
+};
+use gilrs::{Axis, Button, GamepadId, Gilrs};
+
+/// Deadzone and response curve applied to an analog input.
+#[derive(Debug, Clone)]
+pub struct AxisResponse {
+    /// Inputs with magnitude below the deadzone are treated as zero.
+    pub deadzone: f32,
+    /// Exponent of the response curve, values above 1 give finer control around the center.
+    pub exponent: f32,
+}
+
+impl Default for AxisResponse {
+    fn default() -> Self {
+        Self {
+            deadzone: 0.1,
+            exponent: 1.5,
+        }
+    }
+}
+
+impl AxisResponse {
+    pub fn apply(&self, value: f32) -> f32 {
+        let magnitude = value.abs();
+        if magnitude <= self.deadzone {
+            return 0.0;
+        }
+        let scaled = ((magnitude - self.deadzone) / (1.0 - self.deadzone)).min(1.0);
+        value.signum() * scaled.powf(self.exponent)
+    }
+}
+
+/// Steers with the left stick, accelerates with the right trigger and brakes with the left one.
+pub struct GamepadController {
+    gilrs: Gilrs,
+    active: Option<GamepadId>,
+    pub steering: AxisResponse,
+    pub throttle: AxisResponse,
+}
+
+impl GamepadController {
+    pub fn new() -> Result<Self, Box<gilrs::Error>> {
+        Ok(Self {
+            gilrs: Gilrs::new().map_err(Box::new)?,
+            active: None,
+            steering: AxisResponse::default(),
+            throttle: AxisResponse {
+                deadzone: 0.05,
+                exponent: 1.0,
+            },
+        })
+    }
+}
+
+impl Controller for GamepadController {
+    fn control(&mut self, _environment: &Environment) -> Action {
+        // the gamepad used last is the one controlling the car
+        while let Some(event) = self.gilrs.next_event() {
+            self.active = Some(event.id);
+        }
+        let Some(gamepad) = self
+            .active
+            .or_else(|| self.gilrs.gamepads().next().map(|(id, _)| id))
+            .and_then(|id| self.gilrs.connected_gamepad(id))
+        else {
+            return Action {
+                steer: 0.0,
+                throttle: 0.0,
+            };
+        };
+
+        let trigger = |button| {
+            gamepad
+                .button_data(button)
+                .map(|data| data.value())
+                .unwrap_or(0.0)
+        };
+        let steer = -self.steering.apply(gamepad.value(Axis::LeftStickX));
+        let throttle = self
+            .throttle
+            .apply(trigger(Button::RightTrigger2) - trigger(Button::LeftTrigger2));
+
+        Action { steer, throttle }
+    }
+}
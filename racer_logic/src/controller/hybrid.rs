@@ -0,0 +1,67 @@
+use crate::{
+    controller::{Controller, Diagnostics},
+    environment::{Action, Environment},
+};
+use macroquad::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Driver {
+    Policy,
+    Human,
+}
+
+/// Lets a human take over from a policy at any time.
+///
+/// The human takes control as soon as their controller produces a non-zero action and keeps it
+/// until `Tab` is pressed. The policy is queried on every step even while the human drives, so
+/// that it keeps following the state of the episode. Steps driven by the human are reported
+/// as [`Controller::intervention`], which recordings keep for every step.
+pub struct HybridController {
+    policy: Box<dyn Controller>,
+    human: Box<dyn Controller>,
+    driver: Driver,
+}
+
+impl HybridController {
+    pub fn new(policy: Box<dyn Controller>, human: Box<dyn Controller>) -> Self {
+        Self {
+            policy,
+            human,
+            driver: Driver::Policy,
+        }
+    }
+}
+
+impl Controller for HybridController {
+    fn control(&mut self, environment: &Environment) -> Action {
+        let policy_action = self.policy.control(environment);
+        let human_action = self.human.control(environment);
+
+        if human_action.steer != 0.0 || human_action.throttle != 0.0 {
+            self.driver = Driver::Human;
+        } else if is_key_pressed(KeyCode::Tab) {
+            self.driver = Driver::Policy;
+        }
+
+        match self.driver {
+            Driver::Policy => policy_action,
+            Driver::Human => human_action,
+        }
+    }
+
+    fn intervention(&self) -> bool {
+        self.driver == Driver::Human
+    }
+
+    /// Starts the next episode with the policy driving.
+    fn reset(&mut self) {
+        self.policy.reset();
+        self.human.reset();
+        self.driver = Driver::Policy;
+    }
+
+    /// Diagnostics of the policy, which keeps deciding while the human drives.
+    fn diagnostics(&self) -> Option<&Diagnostics> {
+        self.policy.diagnostics()
+    }
+}
@@ -1,19 +1,51 @@
 use crate::{
     controller::Controller,
-    environment::{Action, Observation},
+    environment::{Action, Environment},
 };
 use macroquad::prelude::*;
 
 #[derive(Default)]
-pub struct KeyboardController {}
+pub struct KeyboardController {
+    /// Rate per second at which the inputs follow the pressed keys, immediate when `None`.
+    ramp: Option<f32>,
+    steer: f32,
+    throttle: f32,
+}
+
+impl KeyboardController {
+    /// Keyboard input changing gradually, so that recorded actions are continuous.
+    pub fn ramped(rate: f32) -> Self {
+        Self {
+            ramp: Some(rate),
+            ..Default::default()
+        }
+    }
+}
 
 impl Controller for KeyboardController {
-    fn control(&mut self, _observation: &Observation) -> Action {
+    fn control(&mut self, _environment: &Environment) -> Action {
         let steer =
             ((is_key_down(KeyCode::Left) as i32) - (is_key_down(KeyCode::Right) as i32)) as f32;
         let throttle =
             ((is_key_down(KeyCode::Up) as i32) - (is_key_down(KeyCode::Down) as i32)) as f32;
 
-        Action { steer, throttle }
+        if let Some(rate) = self.ramp {
+            let max_change = rate * get_frame_time();
+            self.steer += (steer - self.steer).clamp(-max_change, max_change);
+            self.throttle += (throttle - self.throttle).clamp(-max_change, max_change);
+        } else {
+            self.steer = steer;
+            self.throttle = throttle;
+        }
+
+        Action {
+            steer: self.steer,
+            throttle: self.throttle,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.steer = 0.0;
+        self.throttle = 0.0;
     }
 }
@@ -1,7 +1,85 @@
-use crate::environment::{Action, Observation};
+use crate::environment::{Action, Environment};
+use std::rc::Rc;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+mod hybrid;
 mod keyboard;
+mod pure_pursuit;
+mod speed;
+mod stanley;
+#[cfg(feature = "gamepad")]
+pub use gamepad::{AxisResponse, GamepadController};
+pub use hybrid::HybridController;
 pub use keyboard::KeyboardController;
+pub use pure_pursuit::PurePursuitController;
+pub use speed::{CONTROL_DT, Pid, SpeedController, SpeedProfile};
+pub use stanley::StanleyController;
 
 pub trait Controller {
-    fn control(&mut self, observation: &Observation) -> Action;
+    fn control(&mut self, environment: &Environment) -> Action;
+
+    /// Whether the last action came from a human correcting the controller.
+    fn intervention(&self) -> bool {
+        false
+    }
+
+    /// Forgets everything remembered from the previous episode.
+    fn reset(&mut self) {}
+
+    /// What the last action was based on, for controllers that can tell.
+    fn diagnostics(&self) -> Option<&Diagnostics> {
+        None
+    }
+}
+
+/// Insight into the last decision of a learned controller, shown by the policy panel.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    /// Label and probability of each discrete action, empty for continuous policies.
+    pub probabilities: Vec<(String, f32)>,
+    /// Index in `probabilities` of the chosen action.
+    pub chosen: Option<usize>,
+    /// Observation as fed to the model, after normalisation and before frame stacking.
+    pub observation: Vec<f32>,
+}
+
+/// Creates the controller of a session when it starts, fails with a message for the player.
+pub type ControllerFactory = Rc<dyn Fn() -> Result<Box<dyn Controller>, String>>;
+
+/// Steering input that turns the front wheels of `car` towards the `target` angle.
+fn steer_towards(target: f32, car: &crate::car::Car) -> f32 {
+    let spec = car.spec();
+    let target = target.clamp(-spec.max_steering_angle, spec.max_steering_angle);
+    let max_change = spec.steering_speed * CONTROL_DT;
+    ((target - car.steering_angle()) / max_change).clamp(-1.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Steps within which the baselines finish a generated track, they need about 70 s.
+    const MAX_STEPS: usize = 2 * 60 * 60;
+
+    fn finishes(mut controller: impl Controller, seed: u64) -> bool {
+        let mut environment = Environment::new(Some(seed));
+        (0..MAX_STEPS).any(|_| {
+            let action = controller.control(&environment);
+            environment.step(&action, true).finished
+        })
+    }
+
+    #[test]
+    fn baselines_finish() {
+        for seed in 0..3 {
+            assert!(
+                finishes(PurePursuitController::default(), seed),
+                "pure pursuit, seed {seed}"
+            );
+            assert!(
+                finishes(StanleyController::default(), seed),
+                "stanley, seed {seed}"
+            );
+        }
+    }
 }
@@ -0,0 +1,48 @@
+use crate::{
+    controller::{Controller, SpeedController, steer_towards},
+    environment::{Action, Environment},
+};
+use macroquad::prelude::*;
+
+/// Steers towards a point on the centerline a speed-dependent distance ahead of the rear axle.
+#[derive(Debug, Clone)]
+pub struct PurePursuitController {
+    pub min_lookahead: f32,
+    /// Additional lookahead distance per unit of velocity.
+    pub lookahead_gain: f32,
+    pub speed: SpeedController,
+}
+
+impl Default for PurePursuitController {
+    fn default() -> Self {
+        Self {
+            min_lookahead: 20.0,
+            lookahead_gain: 0.3,
+            speed: SpeedController::default(),
+        }
+    }
+}
+
+impl Controller for PurePursuitController {
+    fn control(&mut self, environment: &Environment) -> Action {
+        let car = &environment.car;
+        let track = &environment.track;
+        let rear_axle = *car.position();
+        let velocity = *car.velocity();
+        let progress = track.progress(&rear_axle);
+
+        let lookahead = self.min_lookahead + self.lookahead_gain * velocity.max(0.0);
+        let to_target = track.waypoint_at(progress + lookahead).pos - rear_axle;
+        let alpha = Vec2::from_angle(*car.rotation()).angle_between(to_target);
+        let desired = (2.0 * car.wheel_base() * alpha.sin() / to_target.length().max(1.0)).atan();
+
+        Action {
+            steer: steer_towards(desired, car),
+            throttle: self.speed.throttle(track, progress, velocity),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.speed.pid.reset();
+    }
+}
@@ -0,0 +1,135 @@
+use crate::track::Track;
+
+/// Time step the controllers assume between two consecutive calls.
+pub const CONTROL_DT: f32 = 1.0 / 60.0;
+
+#[derive(Debug, Clone)]
+pub struct Pid {
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+    integral: f32,
+    previous_error: Option<f32>,
+}
+
+impl Pid {
+    pub fn new(kp: f32, ki: f32, kd: f32) -> Self {
+        Self {
+            kp,
+            ki,
+            kd,
+            integral: 0.0,
+            previous_error: None,
+        }
+    }
+
+    pub fn update(&mut self, error: f32, dt: f32) -> f32 {
+        self.integral += error * dt;
+        let derivative = self
+            .previous_error
+            .map(|previous| (error - previous) / dt)
+            .unwrap_or(0.0);
+        self.previous_error = Some(error);
+        self.kp * error + self.ki * self.integral + self.kd * derivative
+    }
+
+    pub fn reset(&mut self) {
+        self.integral = 0.0;
+        self.previous_error = None;
+    }
+}
+
+/// Target speed derived from the curvature of the track ahead of the car.
+#[derive(Debug, Clone)]
+pub struct SpeedProfile {
+    pub max_speed: f32,
+    /// Lateral acceleration allowed in turns, limits the speed to `sqrt(a / curvature)`.
+    pub lateral_acceleration: f32,
+    /// Deceleration assumed when braking for an upcoming turn.
+    pub deceleration: f32,
+    /// How far ahead along the centerline the turns are considered.
+    pub horizon: f32,
+}
+
+impl Default for SpeedProfile {
+    fn default() -> Self {
+        Self {
+            max_speed: 150.0,
+            lateral_acceleration: 100.0,
+            deceleration: 40.0,
+            horizon: 250.0,
+        }
+    }
+}
+
+impl SpeedProfile {
+    pub fn target_speed(&self, track: &Track, progress: f32) -> f32 {
+        (0..=(self.horizon / 5.0) as usize)
+            .map(|i| {
+                let ahead = i as f32 * 5.0;
+                let curvature = track.curvature_at(progress + ahead).abs();
+                let turn_speed = if curvature > 0.0 {
+                    (self.lateral_acceleration / curvature).sqrt()
+                } else {
+                    self.max_speed
+                };
+                // speed from which the car can still brake down to the turn speed
+                (turn_speed.powi(2) + 2.0 * self.deceleration * ahead).sqrt()
+            })
+            .fold(self.max_speed, f32::min)
+    }
+}
+
+/// PID throttle control following a [`SpeedProfile`].
+#[derive(Debug, Clone)]
+pub struct SpeedController {
+    pub profile: SpeedProfile,
+    pub pid: Pid,
+}
+
+impl Default for SpeedController {
+    fn default() -> Self {
+        Self {
+            profile: SpeedProfile::default(),
+            pid: Pid::new(0.2, 0.05, 0.0),
+        }
+    }
+}
+
+impl SpeedController {
+    pub fn throttle(&mut self, track: &Track, progress: f32, velocity: f32) -> f32 {
+        let target = self.profile.target_speed(track, progress);
+        self.pid
+            .update(target - velocity, CONTROL_DT)
+            .clamp(-1.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pid_terms() {
+        let mut pid = Pid::new(2.0, 0.5, 0.1);
+        // no derivative on the first update
+        assert!((pid.update(1.0, 0.5) - (2.0 + 0.5 * 0.5)).abs() < 1e-6);
+        // integral 0.5 + 1.5, derivative (3 - 1) / 0.5
+        assert!((pid.update(3.0, 0.5) - (6.0 + 0.5 * 2.0 + 0.1 * 4.0)).abs() < 1e-6);
+
+        pid.reset();
+        assert!((pid.update(-1.0, 0.5) - (-2.0 - 0.5 * 0.5)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn integral_removes_steady_state_error() {
+        // a first order plant driven towards a target against a constant drag
+        let mut pid = Pid::new(0.5, 2.0, 0.0);
+        let (target, mut value) = (10.0, 0.0);
+        for _ in 0..2000 {
+            let input = pid.update(target - value, 0.01);
+            value += (input - 1.0) * 0.01 * 10.0;
+        }
+        assert!((value - target).abs() < 1e-2, "{value}");
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{
+    controller::{Controller, SpeedController, steer_towards},
+    environment::{Action, Environment},
+};
+use macroquad::prelude::*;
+
+/// Stanley lateral control: corrects the heading error and the cross-track error of the front
+/// axle with respect to the closest point on the centerline, on top of the steering angle
+/// required by the curvature of the track.
+#[derive(Debug, Clone)]
+pub struct StanleyController {
+    /// Gain of the cross-track error term.
+    pub gain: f32,
+    /// Velocity added in the denominator of the cross-track term to soften it at low speeds.
+    pub softening: f32,
+    /// Time ahead at which the track curvature is used for the feed-forward steering, so that
+    /// the wheels are already turned when the car enters a turn.
+    pub preview: f32,
+    /// Gain of the damping term on the difference between the yaw rate of the car and the yaw
+    /// rate required by the track, compensating for the time the steering takes to turn.
+    pub yaw_damping: f32,
+    pub speed: SpeedController,
+}
+
+impl Default for StanleyController {
+    fn default() -> Self {
+        Self {
+            gain: 2.0,
+            softening: 5.0,
+            preview: 0.4,
+            yaw_damping: 0.2,
+            speed: SpeedController::default(),
+        }
+    }
+}
+
+impl Controller for StanleyController {
+    fn control(&mut self, environment: &Environment) -> Action {
+        let car = &environment.car;
+        let track = &environment.track;
+        let velocity = *car.velocity();
+        let front_axle = car.position_with_offset(car.wheel_base());
+        let progress = track.progress(&front_axle);
+        let closest = track.waypoint_at(progress);
+
+        let heading_error = Vec2::from_angle(*car.rotation()).angle_between(closest.dir);
+        // positive when the front axle is left of the centerline
+        let cross_track_error = closest.dir.perp_dot(front_axle - closest.pos);
+        let correction = (self.gain * cross_track_error / (velocity.abs() + self.softening)).atan();
+        // steering angle keeping the car on the arc of the upcoming segment
+        let curvature = track.curvature_at(progress + self.preview * velocity.max(0.0));
+        let feed_forward = (car.wheel_base() * curvature).atan();
+        let yaw_rate = velocity * car.steering_angle().tan() / car.wheel_base();
+        let damping = self.yaw_damping * (yaw_rate - velocity * curvature);
+        let desired = feed_forward + heading_error - correction - damping;
+
+        Action {
+            steer: steer_towards(desired, car),
+            throttle: self.speed.throttle(track, progress, velocity),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.speed.pid.reset();
+    }
+}
@@ -0,0 +1,129 @@
+use crate::environment::{Environment, Observation};
+use macroquad::prelude::*;
+use std::iter::zip;
+
+const RAYS: usize = 0;
+const WAYPOINTS: usize = 1;
+const BOXES: usize = 2;
+const COLLISION: usize = 3;
+const WHEELS: usize = 4;
+const OBSERVATION: usize = 5;
+const LAYERS: [(KeyCode, &str); 6] = [
+    (KeyCode::F1, "rays"),
+    (KeyCode::F2, "waypoints"),
+    (KeyCode::F3, "boxes"),
+    (KeyCode::F4, "collision"),
+    (KeyCode::F5, "wheels"),
+    (KeyCode::F6, "observation"),
+];
+
+/// Layers of debug information drawn over the race, each toggled with a function key.
+#[derive(Debug, Clone)]
+pub struct DebugOverlay {
+    enabled: [bool; LAYERS.len()],
+}
+
+impl Default for DebugOverlay {
+    fn default() -> Self {
+        let mut enabled = [false; LAYERS.len()];
+        enabled[RAYS] = true;
+        enabled[WAYPOINTS] = true;
+        Self { enabled }
+    }
+}
+
+impl DebugOverlay {
+    /// Toggles the layers whose keys were pressed.
+    pub fn update(&mut self) {
+        for (enabled, (key, _)) in zip(&mut self.enabled, LAYERS) {
+            if is_key_pressed(key) {
+                *enabled = !*enabled;
+            }
+        }
+    }
+
+    /// Draws the enabled layers in world coordinates, `view` is the visible part of the world.
+    pub fn draw_world(&self, environment: &Environment, view: Rect) {
+        let car = &environment.car;
+        let observation = &environment.observation;
+        if self.enabled[RAYS] {
+            let sensors = &observation.sensors;
+            for (d, (start, end)) in zip(&sensors.distances, &sensors.rays) {
+                draw_line(start.x, start.y, end.x, end.y, 0.3, GREEN.with_alpha(0.2));
+                if let Some(d) = d {
+                    let p = (*end - *start).normalize() * *d + *start;
+                    draw_circle(p.x, p.y, 1.0, RED);
+                }
+            }
+        }
+
+        let segments = environment.track.segments_in(view);
+        if self.enabled[WAYPOINTS] {
+            for segment in &segments {
+                segment.start.draw();
+                segment.end.draw();
+            }
+            let to_waypoint = Vec2::from_angle(*car.rotation()).rotate(
+                Vec2::from_angle(observation.next_waypoint.angle)
+                    * observation.next_waypoint.distance,
+            );
+            let car_pos = car.windshield_position();
+            draw_line(
+                car_pos.x,
+                car_pos.y,
+                car_pos.x + to_waypoint.x,
+                car_pos.y + to_waypoint.y,
+                0.5,
+                GREEN.with_alpha(0.5),
+            );
+        }
+
+        if self.enabled[BOXES] {
+            for segment in &segments {
+                let bbox = segment.bbox();
+                let ([x0, y0], [x1, y1]) = (bbox.lower(), bbox.upper());
+                draw_rectangle_lines(x0, y0, x1 - x0, y1 - y0, 0.5, SKYBLUE);
+            }
+        }
+
+        if self.enabled[COLLISION] {
+            car.bbox().draw_lines(0.5, MAGENTA);
+            if let Some(finish) = environment.track.finish_area() {
+                finish.draw_lines(0.5, MAGENTA);
+            }
+        }
+
+        if self.enabled[WHEELS] {
+            for (pos, on_track) in zip(car.wheel_positions(), observation.wheels_on_track) {
+                let color = if on_track { GREEN } else { RED };
+                draw_circle(pos.x, pos.y, 1.5, color);
+            }
+        }
+    }
+
+    /// Draws the observation and the legend of the layers in screen coordinates.
+    pub fn draw_screen(&self, observation: &Observation, reward: f32) {
+        push_camera_state();
+        set_default_camera();
+        if self.enabled[OBSERVATION] {
+            let values: Vec<f32> = observation.clone().into();
+            let mut text = format!(
+                "next_waypoint: {:.2}\nspeed: {:.2}\nreward: {reward:.2}\n",
+                observation.next_waypoint.angle, observation.velocity
+            );
+            for (name, value) in zip(Observation::feature_names(), values) {
+                text.push_str(&format!("\n{name}: {value:.3}"));
+            }
+            draw_multiline_text(&text, 5.0, 80.0, 16.0, None, YELLOW.with_alpha(0.8));
+        }
+
+        let mut x = 5.0;
+        for (enabled, (key, name)) in zip(self.enabled, LAYERS) {
+            let label = format!("{key:?} {name}");
+            let color = if enabled { YELLOW } else { GRAY };
+            draw_text(&label, x, screen_height() - 8.0, 16.0, color);
+            x += measure_text(&label, None, 16, 1.0).width + 12.0;
+        }
+        pop_camera_state();
+    }
+}
@@ -4,19 +4,29 @@ use std::{
 };
 
 use crate::{
-    car::Car,
+    car::{Car, CarSpec},
     follow_camera::FollowCamera,
+    pixels::PixelObservation,
+    raster::Raster,
     track::{Track, sensor_readings},
 };
 use macroquad::prelude::*;
 
 pub const SENSOR_REACH: f32 = 205.0;
+/// Where the car stands at the start of every track.
+const START: Vec2 = vec2(0.0, 15.0);
+pub const SENSOR_COUNT: usize = 13;
+/// Version of the observation vector layout, bumped whenever the features change.
+pub const OBSERVATION_VERSION: u32 = 1;
 
 pub struct Environment {
+    pub seed: u64,
     pub track: Track,
     pub car: Car,
     pub observation: Observation,
     rewarded_waypoints: HashSet<(i32, i32)>,
+    /// Image observed besides the features, see [`Environment::with_pixels`].
+    pixels: Option<PixelObservation>,
 }
 
 #[derive(Debug, Clone)]
@@ -52,6 +62,26 @@ pub struct Outcome {
     pub reward: f32,
 }
 
+impl Observation {
+    /// Names of the features in the vector produced by `Vec::<f32>::from(observation)`.
+    pub fn feature_names() -> Vec<String> {
+        let mut names: Vec<String> = [
+            "velocity",
+            "steering_angle",
+            "next_wp_angle",
+            "next_wp_dist",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+        names.extend(
+            ["front_r", "front_l", "rear_r", "rear_l"].map(|w| format!("wheel_on_track_{w}")),
+        );
+        names.extend((0..SENSOR_COUNT).map(|i| format!("sensor_readings_{i}")));
+        names
+    }
+}
+
 impl From<Observation> for Vec<f32> {
     fn from(o: Observation) -> Vec<f32> {
         let mut ans = vec![
@@ -81,23 +111,71 @@ impl Environment {
         });
         macroquad::rand::srand(seed);
 
-        let car = Car::new(0.0, 15.0);
         let mut track = Track::new();
         for _ in 0..100 {
             track.add_random_shape();
         }
         track.add_finish();
         track.compute_rtree();
+        Self::with_track(track, seed)
+    }
+
+    /// Environment on a given track, e.g. one loaded with [`Track::load`]. `seed` only labels
+    /// the episode.
+    pub fn with_track(track: Track, seed: u64) -> Self {
+        let car = Car::new(START.x, START.y);
         let observation = Environment::observe(&car, &track);
         let wp_key = Environment::get_nearest_waypoint(&track, &car);
         Self {
+            seed,
             car,
             track,
             observation,
             rewarded_waypoints: [wp_key].into(),
+            pixels: None,
         }
     }
 
+    /// Replaces the car with a standing one handling like `spec`.
+    pub fn with_car(mut self, spec: CarSpec) -> Self {
+        self.car = Car::with_spec(START.x, START.y, spec);
+        self.observation = Environment::observe(&self.car, &self.track);
+        self
+    }
+
+    /// Also observes the track around the car as an image, see [`Environment::pixels`].
+    pub fn with_pixels(mut self, pixels: PixelObservation) -> Self {
+        self.pixels = Some(pixels);
+        self
+    }
+
+    /// Image around the car in its current state, when observed.
+    pub fn pixels(&self) -> Option<Vec<u8>> {
+        self.pixels.as_ref().map(|pixels| pixels.observe(self))
+    }
+
+    pub fn pixel_observation(&self) -> Option<&PixelObservation> {
+        self.pixels.as_ref()
+    }
+
+    /// Starts a new episode on the same track with the same car.
+    pub fn restart(&mut self) {
+        self.car = self.car.respawned(START.x, START.y);
+        self.observation = Environment::observe(&self.car, &self.track);
+        self.rewarded_waypoints =
+            [Environment::get_nearest_waypoint(&self.track, &self.car)].into();
+    }
+
+    /// Starts a new episode on a new random track with the same car and observations.
+    pub fn new_track(&mut self) {
+        let car = self.car.respawned(START.x, START.y);
+        let pixels = self.pixels.take();
+        *self = Environment::new(None);
+        self.car = car;
+        self.pixels = pixels;
+        self.observation = Environment::observe(&self.car, &self.track);
+    }
+
     fn sensor_readings(car: &Car, track: &Track) -> SensorReadings {
         let x = car.position_with_offset(SENSOR_REACH * 0.5);
         let nearest_segments = track.nearest_segments(&x, 5);
@@ -174,10 +252,37 @@ impl Environment {
         Outcome { finished, reward }
     }
 
+    /// Fraction of the centerline driven so far, between zero and one.
+    pub fn progress(&self) -> f32 {
+        (self.track.progress(self.car.position()) / self.track.length()).clamp(0.0, 1.0)
+    }
+
+    /// Draws the part of the track in `view` and the car without a window, with `scale` pixels
+    /// per world unit.
+    pub fn raster(&self, view: Rect, scale: f32) -> Raster {
+        let mut raster = Raster::new(view, scale, DARKGREEN);
+        self.track.raster(&mut raster);
+        self.car.raster(&mut raster);
+        raster
+    }
+
+    /// Image of `width` by `height` pixels centered on the car, north up, with `scale` pixels
+    /// per world unit.
+    pub fn frame(&self, width: usize, height: usize, scale: f32) -> Raster {
+        let size = vec2(width as f32, height as f32) / scale;
+        let view = Rect::new(
+            self.car.position().x - size.x / 2.0,
+            self.car.position().y - size.y / 2.0,
+            size.x,
+            size.y,
+        );
+        self.raster(view, scale)
+    }
+
     pub fn draw(&self, follow_camera: &mut FollowCamera) {
         clear_background(DARKGREEN);
-        follow_camera.update(&self.car);
-        self.track.draw(&self.car);
+        follow_camera.update(&self.car, &self.track);
+        self.track.draw();
         self.car.draw();
     }
 }
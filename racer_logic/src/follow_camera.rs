@@ -1,39 +1,190 @@
-use crate::car::Car;
+use crate::{car::Car, track::Track, utils::parse_name};
 use macroquad::prelude::*;
-use std::f32::consts::FRAC_PI_2;
+use std::{f32::consts::FRAC_PI_2, str::FromStr};
+
+const MIN_ZOOM: f32 = 1.0;
+const MAX_ZOOM: f32 = 40.0;
+
+/// How the camera follows the car, cycled with `C`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CameraMode {
+    /// Behind the car, rotating with it.
+    #[default]
+    Chase,
+    /// Centered on the car with a fixed orientation.
+    NorthUp,
+    /// The whole track.
+    Overview,
+    /// Moved by dragging with the mouse.
+    Free,
+}
+
+impl CameraMode {
+    pub const ALL: [CameraMode; 4] = [
+        CameraMode::Chase,
+        CameraMode::NorthUp,
+        CameraMode::Overview,
+        CameraMode::Free,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CameraMode::Chase => "chase",
+            CameraMode::NorthUp => "north-up",
+            CameraMode::Overview => "overview",
+            CameraMode::Free => "free",
+        }
+    }
+
+    fn next(self) -> Self {
+        let index = CameraMode::ALL.iter().position(|&mode| mode == self);
+        CameraMode::ALL[index.map_or(0, |i| (i + 1) % CameraMode::ALL.len())]
+    }
+}
+
+impl FromStr for CameraMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_name(s, "camera mode", &Self::ALL, Self::name)
+    }
+}
+
+/// Initial mode and zoom of the camera and how quickly it catches up with the car.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraConfig {
+    pub mode: CameraMode,
+    /// Pixels per world unit are half the zoom, changed with the mouse wheel.
+    pub zoom: f32,
+    /// Fraction of the distance to its target the camera moves per second, infinite to stay
+    /// right on target.
+    pub position_rate: f32,
+    /// Same as `position_rate` for the rotation of the chase camera.
+    pub rotation_rate: f32,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            mode: CameraMode::Chase,
+            zoom: 8.0,
+            position_rate: 5.0,
+            rotation_rate: 1.0,
+        }
+    }
+}
 
 pub struct FollowCamera {
-    zoom: f32,
+    config: CameraConfig,
     camera_2d: Camera2D,
+    /// World position held under the mouse cursor while dragging in free mode.
+    grab: Option<Vec2>,
 }
 
 impl FollowCamera {
-    pub fn new(car: &Car) -> Self {
-        let zoom = 8.0;
+    pub fn new(car: &Car, config: CameraConfig) -> Self {
+        let zoom = config.zoom;
+        let rotation = match config.mode {
+            CameraMode::Chase => -car.rotation().to_degrees() + 90.0,
+            _ => 0.0,
+        };
         let camera_2d = Camera2D {
             target: *car.position(),
             zoom: vec2(zoom / screen_width(), -zoom / screen_height()),
-            rotation: -car.rotation().to_degrees() + 90.0,
+            rotation,
             ..Default::default()
         };
-        Self { zoom, camera_2d }
+        Self {
+            config,
+            camera_2d,
+            grab: None,
+        }
+    }
+
+    /// Jumps to the car, e.g. after it was put back to the start, keeping mode and zoom.
+    pub fn reset(&mut self, car: &Car) {
+        *self = FollowCamera::new(car, self.config);
     }
 
-    pub fn update(&mut self, car: &Car) {
-        let car_rotation = car.rotation() - FRAC_PI_2;
-        let target = car.position_with_offset(50.0);
+    pub fn mode(&self) -> CameraMode {
+        self.config.mode
+    }
+
+    /// Handles the camera keys and the mouse, then moves the camera for this frame.
+    pub fn update(&mut self, car: &Car, track: &Track) {
+        if is_key_pressed(KeyCode::C) {
+            self.config.mode = self.config.mode.next();
+            self.grab = None;
+        }
+        let (_, wheel) = mouse_wheel();
+        if wheel != 0.0 && self.config.mode != CameraMode::Overview {
+            self.config.zoom =
+                (self.config.zoom * 1.1f32.powf(wheel.signum())).clamp(MIN_ZOOM, MAX_ZOOM);
+        }
+
         let dt = get_frame_time();
-        self.camera_2d.rotation = self.camera_2d.rotation.lerp(-car_rotation.to_degrees(), dt);
-        self.camera_2d.target = self.camera_2d.target.lerp(target, 5.0 * dt);
-        self.camera_2d.zoom = vec2(self.zoom / screen_width(), -self.zoom / screen_height());
+        let position_step = (self.config.position_rate * dt).min(1.0);
+        let rotation_step = (self.config.rotation_rate * dt).min(1.0);
+        let zoom = self.config.zoom;
+        match self.config.mode {
+            CameraMode::Chase => {
+                let car_rotation = car.rotation() - FRAC_PI_2;
+                let target = car.position_with_offset(50.0);
+                self.camera_2d.rotation = self
+                    .camera_2d
+                    .rotation
+                    .lerp(-car_rotation.to_degrees(), rotation_step);
+                self.camera_2d.target = self.camera_2d.target.lerp(target, position_step);
+                self.camera_2d.zoom = vec2(zoom / screen_width(), -zoom / screen_height());
+            }
+            CameraMode::NorthUp => {
+                self.camera_2d.rotation = 0.0;
+                self.camera_2d.target = self.camera_2d.target.lerp(*car.position(), position_step);
+                self.camera_2d.zoom = vec2(zoom / screen_width(), -zoom / screen_height());
+            }
+            CameraMode::Overview => {
+                let bounds = track.bounds();
+                // pixels per world unit fitting the track with a small border
+                let scale = 0.95 * (screen_width() / bounds.w).min(screen_height() / bounds.h);
+                self.camera_2d.rotation = 0.0;
+                self.camera_2d.target = bounds.center();
+                self.camera_2d.zoom =
+                    vec2(2.0 * scale / screen_width(), -2.0 * scale / screen_height());
+            }
+            CameraMode::Free => {
+                self.camera_2d.rotation = 0.0;
+                self.camera_2d.zoom = vec2(zoom / screen_width(), -zoom / screen_height());
+                if is_mouse_button_down(MouseButton::Left) {
+                    let cursor = self.camera_2d.screen_to_world(mouse_position().into());
+                    let grab = *self.grab.get_or_insert(cursor);
+                    self.camera_2d.target += grab - cursor;
+                } else {
+                    self.grab = None;
+                }
+            }
+        }
         set_camera(&self.camera_2d);
     }
+
+    /// Rectangle in world coordinates containing everything on the screen.
+    pub fn view(&self) -> Rect {
+        let corners = [
+            vec2(0.0, 0.0),
+            vec2(screen_width(), 0.0),
+            vec2(0.0, screen_height()),
+            vec2(screen_width(), screen_height()),
+        ]
+        .map(|corner| self.camera_2d.screen_to_world(corner));
+        let min = corners.iter().fold(Vec2::MAX, |min, p| min.min(*p));
+        let max = corners.iter().fold(Vec2::MIN, |max, p| max.max(*p));
+        Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
 }
 
 impl Clone for FollowCamera {
     fn clone(&self) -> Self {
         Self {
-            zoom: self.zoom,
+            config: self.config,
             camera_2d: Camera2D {
                 rotation: self.camera_2d.rotation,
                 zoom: self.camera_2d.zoom,
@@ -42,6 +193,7 @@ impl Clone for FollowCamera {
                 render_target: self.camera_2d.render_target.clone(),
                 viewport: self.camera_2d.viewport,
             },
+            grab: self.grab,
         }
     }
 }
@@ -1,8 +1,17 @@
-mod car;
+pub mod car;
 pub mod controller;
+mod debug_overlay;
 pub mod environment;
-mod follow_camera;
+pub mod follow_camera;
+pub mod metrics;
+mod minimap;
 mod physics;
+pub mod pixels;
+mod policy_panel;
+pub mod raster;
+pub mod recorder;
 pub mod states;
-mod track;
+pub mod track;
+pub mod trajectory;
+mod trajectory_overlay;
 mod utils;
@@ -0,0 +1,310 @@
+use crate::{
+    controller::CONTROL_DT,
+    environment::{Environment, Outcome},
+};
+use serde::Serialize;
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+const CSV_HEADER: &str = "episode,seed,steps,return,finished,finish_time,termination,progress,\
+off_track_time,crashes,track";
+
+/// Why an episode ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Termination {
+    /// The car crossed the finish line.
+    Finished,
+    /// The episode ran out of steps.
+    StepLimit,
+    /// The episode was stopped from outside before finishing, e.g. by a reset.
+    Truncated,
+}
+
+impl Termination {
+    fn as_str(self) -> &'static str {
+        match self {
+            Termination::Finished => "finished",
+            Termination::StepLimit => "step_limit",
+            Termination::Truncated => "truncated",
+        }
+    }
+}
+
+/// Metrics of one episode, accumulated step by step.
+#[derive(Debug, Clone, Serialize)]
+pub struct EpisodeMetrics {
+    pub episode: u64,
+    /// Seed of the track.
+    pub seed: u64,
+    pub steps: u64,
+    /// Undiscounted sum of the rewards.
+    #[serde(rename = "return")]
+    pub reward: f32,
+    pub finished: bool,
+    /// Simulated seconds until the finish.
+    pub finish_time: Option<f32>,
+    pub termination: Termination,
+    /// Fraction of the centerline driven, one when finished.
+    pub progress: f32,
+    /// Simulated seconds with at least one wheel off the track.
+    pub off_track_time: f32,
+    /// Number of times the car left the track with all four wheels.
+    pub crashes: u32,
+    /// Seed of the generated track or path of the track file.
+    pub track: String,
+    /// Whether all four wheels were off the track after the last step.
+    #[serde(skip)]
+    crashed: bool,
+}
+
+impl EpisodeMetrics {
+    pub fn new(episode: u64, seed: u64) -> Self {
+        Self {
+            episode,
+            seed,
+            steps: 0,
+            reward: 0.0,
+            finished: false,
+            finish_time: None,
+            termination: Termination::Truncated,
+            progress: 0.0,
+            off_track_time: 0.0,
+            crashes: 0,
+            track: seed.to_string(),
+            crashed: false,
+        }
+    }
+
+    /// Accounts for one step of the episode, taken with a fixed time step, that led to
+    /// `environment`.
+    pub fn step(&mut self, environment: &Environment, outcome: &Outcome) {
+        self.steps += 1;
+        self.reward += outcome.reward;
+        let wheels = &environment.observation.wheels_on_track;
+        if wheels.contains(&false) {
+            self.off_track_time += CONTROL_DT;
+        }
+        let crashed = !wheels.contains(&true);
+        if crashed && !self.crashed {
+            self.crashes += 1;
+        }
+        self.crashed = crashed;
+        if outcome.finished && !self.finished {
+            self.finished = true;
+            self.finish_time = Some(self.steps as f32 * CONTROL_DT);
+        }
+    }
+
+    /// Completes the metrics with the environment the episode ended in. `termination` is only
+    /// used for unfinished episodes.
+    pub fn end(&mut self, environment: &Environment, termination: Termination) {
+        if self.finished {
+            self.termination = Termination::Finished;
+            self.progress = 1.0;
+        } else {
+            self.termination = termination;
+            self.progress = environment.progress();
+        }
+    }
+}
+
+enum Format {
+    JsonLines,
+    Csv,
+}
+
+/// Writes the [`EpisodeMetrics`] of every episode to a file, one line per episode.
+///
+/// The format is chosen by the extension of the output file: `.jsonl` files have one JSON
+/// object per line, `.csv` files have a header with the column names. Existing files are
+/// appended to, so that resumed runs continue their log.
+pub struct MetricsLogger {
+    format: Format,
+    writer: BufWriter<File>,
+}
+
+impl MetricsLogger {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let format = match path.extension().and_then(|e| e.to_str()) {
+            Some("jsonl") => Format::JsonLines,
+            Some("csv") => Format::Csv,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unsupported metrics format: {}", path.display()),
+                ));
+            }
+        };
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let empty = file.metadata()?.len() == 0;
+        let mut writer = BufWriter::new(file);
+        if empty && matches!(format, Format::Csv) {
+            writeln!(writer, "{CSV_HEADER}")?;
+        }
+        Ok(Self { format, writer })
+    }
+
+    /// Writes the metrics of one episode and flushes them, so that the file can be followed
+    /// while the run goes on.
+    pub fn log(&mut self, metrics: &EpisodeMetrics) -> io::Result<()> {
+        match self.format {
+            Format::JsonLines => {
+                serde_json::to_writer(&mut self.writer, metrics)?;
+                writeln!(self.writer)?;
+            }
+            Format::Csv => {
+                let finish_time = metrics.finish_time.map_or(String::new(), |t| t.to_string());
+                writeln!(
+                    self.writer,
+                    "{},{},{},{},{},{finish_time},{},{},{},{},{}",
+                    metrics.episode,
+                    metrics.seed,
+                    metrics.steps,
+                    metrics.reward,
+                    metrics.finished,
+                    metrics.termination.as_str(),
+                    metrics.progress,
+                    metrics.off_track_time,
+                    metrics.crashes,
+                    csv_field(&metrics.track)
+                )?;
+            }
+        }
+        self.writer.flush()
+    }
+}
+
+/// `value` as a CSV field, quoted when it contains a separator, a quote or a line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn outcome(finished: bool) -> Outcome {
+        Outcome {
+            finished,
+            reward: 1.0,
+        }
+    }
+
+    /// Steps `metrics` with the given wheels on the track.
+    fn step(metrics: &mut EpisodeMetrics, environment: &mut Environment, wheels: [bool; 4]) {
+        environment.observation.wheels_on_track = wheels;
+        metrics.step(environment, &outcome(false));
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("tracks/a.track"), "tracks/a.track");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("cr\r"), "\"cr\r\"");
+    }
+
+    #[test]
+    fn crashes_count_leaving_the_track() {
+        let mut environment = Environment::new(Some(1));
+        let mut metrics = EpisodeMetrics::new(0, 1);
+        step(&mut metrics, &mut environment, [true; 4]);
+        step(&mut metrics, &mut environment, [false, true, true, true]);
+        assert_eq!(metrics.crashes, 0);
+        step(&mut metrics, &mut environment, [false; 4]);
+        step(&mut metrics, &mut environment, [false; 4]);
+        assert_eq!(metrics.crashes, 1);
+        step(&mut metrics, &mut environment, [true, false, false, false]);
+        step(&mut metrics, &mut environment, [false; 4]);
+        assert_eq!(metrics.crashes, 2);
+        assert_eq!(metrics.steps, 6);
+        assert!((metrics.off_track_time - 5.0 * CONTROL_DT).abs() < 1e-6);
+        assert_eq!(metrics.reward, 6.0);
+    }
+
+    #[test]
+    fn finishing_overrides_the_termination() {
+        let environment = Environment::new(Some(1));
+        let mut metrics = EpisodeMetrics::new(0, 1);
+        metrics.step(&environment, &outcome(false));
+        metrics.step(&environment, &outcome(true));
+        // steps after the finish do not move it
+        metrics.step(&environment, &outcome(true));
+        metrics.end(&environment, Termination::StepLimit);
+        assert!(metrics.finished);
+        assert_eq!(metrics.finish_time, Some(2.0 * CONTROL_DT));
+        assert_eq!(metrics.termination, Termination::Finished);
+        assert_eq!(metrics.progress, 1.0);
+    }
+
+    #[test]
+    fn unfinished_episodes_keep_the_termination() {
+        let environment = Environment::new(Some(1));
+        let mut metrics = EpisodeMetrics::new(0, 1);
+        metrics.step(&environment, &outcome(false));
+        metrics.end(&environment, Termination::Truncated);
+        assert!(!metrics.finished);
+        assert_eq!(metrics.finish_time, None);
+        assert_eq!(metrics.termination, Termination::Truncated);
+        assert_eq!(metrics.progress, environment.progress());
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("racer-metrics-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn csv_logs_have_one_header() {
+        let path = temp_path("log.csv");
+        let mut metrics = EpisodeMetrics::new(3, 7);
+        metrics.track = "a,b.track".to_owned();
+        for _ in 0..2 {
+            MetricsLogger::create(&path).unwrap().log(&metrics).unwrap();
+        }
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines[1], "3,7,0,0,false,,truncated,0,0,0,\"a,b.track\"",);
+        assert_eq!(lines[1], lines[2]);
+    }
+
+    #[test]
+    fn jsonl_logs_have_one_object_per_line() {
+        let path = temp_path("log.jsonl");
+        let mut logger = MetricsLogger::create(&path).unwrap();
+        logger.log(&EpisodeMetrics::new(0, 1)).unwrap();
+        logger.log(&EpisodeMetrics::new(1, 2)).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["seed"], 2);
+        assert_eq!(lines[1]["termination"], "truncated");
+        assert!(lines[1].get("crashed").is_none());
+    }
+
+    #[test]
+    fn unknown_extensions_are_rejected() {
+        let path = temp_path("log.txt");
+        let error = MetricsLogger::create(&path).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,67 @@
+use crate::{car::Car, track::Track};
+use macroquad::prelude::*;
+
+/// Largest side of the minimap in pixels.
+const SIZE: f32 = 200.0;
+const MARGIN: f32 = 10.0;
+
+/// Overlay in the top right corner showing the whole track, the finish and the car.
+#[derive(Clone)]
+pub struct Minimap {
+    /// Toggled with `M`.
+    visible: bool,
+    centerline: Vec<Vec2>,
+    /// Index of the first centerline point on the finish straight.
+    finish: usize,
+    bounds: Rect,
+}
+
+impl Minimap {
+    pub fn new(track: &Track) -> Self {
+        let spacing = 10.0;
+        let centerline = track.centerline(spacing);
+        let finish = track
+            .finish_distance()
+            .map_or(centerline.len(), |distance| (distance / spacing) as usize);
+        Self {
+            visible: true,
+            centerline,
+            finish,
+            bounds: track.bounds(),
+        }
+    }
+
+    pub fn draw(&mut self, car: &Car) {
+        if is_key_pressed(KeyCode::M) {
+            self.visible = !self.visible;
+        }
+        if !self.visible || self.centerline.is_empty() {
+            return;
+        }
+
+        let scale = (SIZE / self.bounds.w).min(SIZE / self.bounds.h);
+        let size = self.bounds.size() * scale;
+        let origin = vec2(screen_width() - MARGIN - size.x, MARGIN);
+        let to_screen = |p: Vec2| origin + (p - self.bounds.point()) * scale;
+
+        push_camera_state();
+        set_default_camera();
+        draw_rectangle(
+            origin.x - 4.0,
+            origin.y - 4.0,
+            size.x + 8.0,
+            size.y + 8.0,
+            BLACK.with_alpha(0.5),
+        );
+        for (i, points) in self.centerline.windows(2).enumerate() {
+            let (start, end) = (to_screen(points[0]), to_screen(points[1]));
+            let color = if i >= self.finish { WHITE } else { GRAY };
+            draw_line(start.x, start.y, end.x, end.y, 2.0, color);
+        }
+        let position = to_screen(*car.position());
+        let heading = position + Vec2::from_angle(*car.rotation()) * 8.0;
+        draw_line(position.x, position.y, heading.x, heading.y, 2.0, RED);
+        draw_circle(position.x, position.y, 4.0, RED);
+        pop_camera_state();
+    }
+}
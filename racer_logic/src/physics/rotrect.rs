@@ -1,6 +1,6 @@
 use macroquad::prelude::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RotRect {
     center: Vec2,
     half_size: Vec2,
@@ -38,6 +38,21 @@ impl RotRect {
             .unwrap();
     }
 
+    /// Corners in order around the rectangle.
+    pub fn outline(&self) -> [Vec2; 4] {
+        // corners are ordered by the signs of their offsets, not around the rectangle
+        [0, 1, 3, 2].map(|i| self.corners[i])
+    }
+
+    /// Draws the outline of the rectangle.
+    pub fn draw_lines(&self, thickness: f32, color: Color) {
+        let outline = self.outline();
+        for (i, a) in outline.iter().enumerate() {
+            let b = outline[(i + 1) % outline.len()];
+            draw_line(a.x, a.y, b.x, b.y, thickness, color);
+        }
+    }
+
     fn get_corners(center: &Vec2, half_size: &Vec2, rotation: f32) -> Vec<Vec2> {
         [
             vec2(1.0, 1.0),
@@ -0,0 +1,98 @@
+use crate::{environment::Environment, raster::Raster, utils::parse_name};
+use macroquad::prelude::*;
+use std::str::FromStr;
+
+/// What a pixel observation shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PixelMode {
+    /// One channel, 255 on the track surface, where
+    /// [`Track::on_track`](crate::track::Track::on_track) holds, and 0 off it.
+    #[default]
+    Occupancy,
+    /// Three channels, the track and the car as drawn by [`Environment::raster`].
+    Rgb,
+}
+
+impl PixelMode {
+    pub const ALL: [PixelMode; 2] = [PixelMode::Occupancy, PixelMode::Rgb];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PixelMode::Occupancy => "occupancy",
+            PixelMode::Rgb => "rgb",
+        }
+    }
+
+    pub fn channels(self) -> usize {
+        match self {
+            PixelMode::Occupancy => 1,
+            PixelMode::Rgb => 3,
+        }
+    }
+}
+
+impl FromStr for PixelMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_name(s, "pixel mode", &Self::ALL, Self::name)
+    }
+}
+
+/// Top-down image centered on the car and rotated so that the car points up, an alternative to
+/// the ray sensors for policies that learn from pixels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PixelObservation {
+    pub width: usize,
+    pub height: usize,
+    /// Pixels per world unit.
+    pub scale: f32,
+    pub mode: PixelMode,
+}
+
+impl Default for PixelObservation {
+    /// 64 by 64 pixels covering about three track widths around the car.
+    fn default() -> Self {
+        Self {
+            width: 64,
+            height: 64,
+            scale: 0.5,
+            mode: PixelMode::default(),
+        }
+    }
+}
+
+impl PixelObservation {
+    /// Row-major pixels of `environment` with [`PixelMode::channels`] bytes per pixel.
+    pub fn observe(&self, environment: &Environment) -> Vec<u8> {
+        let car = &environment.car;
+        let background = match self.mode {
+            PixelMode::Occupancy => BLACK,
+            PixelMode::Rgb => DARKGREEN,
+        };
+        let mut raster = Raster::around(
+            *car.position(),
+            *car.rotation(),
+            self.width,
+            self.height,
+            self.scale,
+            background,
+        );
+        match self.mode {
+            // a mask of the surface alone, whatever the colors of the track
+            PixelMode::Occupancy => {
+                environment.track.raster_surface(&mut raster, WHITE);
+                raster
+                    .rgba()
+                    .chunks_exact(4)
+                    .map(|pixel| pixel[0])
+                    .collect()
+            }
+            PixelMode::Rgb => {
+                environment.track.raster(&mut raster);
+                car.raster(&mut raster);
+                raster.rgb()
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{controller::Diagnostics, environment::Action};
+use macroquad::prelude::*;
+use std::collections::VecDeque;
+
+const WIDTH: f32 = 260.0;
+const MARGIN: f32 = 10.0;
+/// Top of the panel, below the minimap.
+const TOP: f32 = 230.0;
+const FONT_SIZE: f32 = 14.0;
+const ROW: f32 = 12.0;
+/// Steps kept for the rolling plots.
+const HISTORY: usize = 300;
+/// Normalized features are drawn up to this magnitude.
+const FEATURE_RANGE: f32 = 3.0;
+const PLOT_HEIGHT: f32 = 40.0;
+
+/// Panel on the right explaining the decisions of a learned controller: the probability of
+/// every action, the chosen action, the normalized observation and the recent reward and speed.
+#[derive(Debug)]
+pub struct PolicyPanel {
+    /// Toggled with `I`.
+    visible: bool,
+    action: Option<Action>,
+    /// Reward and velocity of the last [`HISTORY`] steps.
+    history: VecDeque<(f32, f32)>,
+}
+
+impl Default for PolicyPanel {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            action: None,
+            history: VecDeque::with_capacity(HISTORY),
+        }
+    }
+}
+
+impl PolicyPanel {
+    /// Forgets the previous race.
+    pub fn clear(&mut self) {
+        self.action = None;
+        self.history.clear();
+    }
+
+    /// Toggles the panel when `I` was pressed.
+    pub fn update(&mut self) {
+        if is_key_pressed(KeyCode::I) {
+            self.visible = !self.visible;
+        }
+    }
+
+    /// Remembers the action of a step with its reward and the velocity it led to.
+    pub fn push(&mut self, action: &Action, reward: f32, velocity: f32) {
+        self.action = Some(Action {
+            steer: action.steer,
+            throttle: action.throttle,
+        });
+        if self.history.len() == HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back((reward, velocity));
+    }
+
+    /// Draws the panel, only for controllers exposing their [`Diagnostics`].
+    pub fn draw(&self, diagnostics: Option<&Diagnostics>) {
+        let Some(diagnostics) = diagnostics.filter(|_| self.visible) else {
+            return;
+        };
+        let x = screen_width() - MARGIN - WIDTH;
+        let mut y = TOP;
+
+        push_camera_state();
+        set_default_camera();
+        let height = self.height(diagnostics);
+        draw_rectangle(
+            x - 4.0,
+            y - 4.0,
+            WIDTH + 8.0,
+            height + 8.0,
+            BLACK.with_alpha(0.5),
+        );
+
+        if !diagnostics.probabilities.is_empty() {
+            draw_label("action probabilities (steer, throttle)", x, &mut y, WHITE);
+            for (i, (label, probability)) in diagnostics.probabilities.iter().enumerate() {
+                let chosen = diagnostics.chosen == Some(i);
+                let color = if chosen { ORANGE } else { SKYBLUE };
+                draw_text(label, x, y + ROW - 3.0, FONT_SIZE, color);
+                let bar = (WIDTH - 130.0) * probability.clamp(0.0, 1.0);
+                draw_rectangle(x + 80.0, y + 2.0, bar, ROW - 4.0, color);
+                draw_text(
+                    &format!("{probability:.2}"),
+                    x + WIDTH - 40.0,
+                    y + ROW - 3.0,
+                    FONT_SIZE,
+                    color,
+                );
+                y += ROW;
+            }
+            y += ROW / 2.0;
+        }
+
+        if let Some(action) = &self.action {
+            let text = format!(
+                "action: steer {:+.2}, throttle {:+.2}",
+                action.steer, action.throttle
+            );
+            draw_label(&text, x, &mut y, ORANGE);
+            y += ROW / 2.0;
+        }
+
+        draw_label("normalized observation", x, &mut y, WHITE);
+        draw_features(&diagnostics.observation, x, &mut y);
+
+        let rewards: Vec<f32> = self.history.iter().map(|(reward, _)| *reward).collect();
+        let velocities: Vec<f32> = self.history.iter().map(|(_, velocity)| *velocity).collect();
+        draw_plot("reward", &rewards, x, &mut y, YELLOW);
+        draw_plot("velocity", &velocities, x, &mut y, SKYBLUE);
+        pop_camera_state();
+    }
+
+    /// Height of the content of [`PolicyPanel::draw`].
+    fn height(&self, diagnostics: &Diagnostics) -> f32 {
+        let mut height = 3.0 * (ROW + PLOT_HEIGHT + ROW / 2.0);
+        if !diagnostics.probabilities.is_empty() {
+            height += (diagnostics.probabilities.len() as f32 + 1.5) * ROW;
+        }
+        if self.action.is_some() {
+            height += 1.5 * ROW;
+        }
+        height
+    }
+}
+
+fn draw_label(text: &str, x: f32, y: &mut f32, color: Color) {
+    draw_text(text, x, *y + ROW - 3.0, FONT_SIZE, color);
+    *y += ROW;
+}
+
+/// One bar per feature around a zero line, features beyond [`FEATURE_RANGE`] are clipped red.
+fn draw_features(features: &[f32], x: f32, y: &mut f32) {
+    let width = WIDTH / features.len().max(1) as f32;
+    let middle = *y + PLOT_HEIGHT / 2.0;
+    draw_rectangle_lines(x, *y, WIDTH, PLOT_HEIGHT, 1.0, GRAY);
+    draw_line(x, middle, x + WIDTH, middle, 1.0, GRAY);
+    for (i, value) in features.iter().enumerate() {
+        let height = PLOT_HEIGHT / 2.0 * value.clamp(-FEATURE_RANGE, FEATURE_RANGE) / FEATURE_RANGE;
+        let color = if value.abs() > FEATURE_RANGE {
+            RED
+        } else {
+            GREEN
+        };
+        let left = x + i as f32 * width + 1.0;
+        draw_rectangle(
+            left,
+            middle.min(middle - height),
+            width - 2.0,
+            height.abs(),
+            color,
+        );
+    }
+    *y += PLOT_HEIGHT + ROW / 2.0;
+}
+
+/// Line plot of `values` scaled to their range, with the latest value in the title.
+fn draw_plot(name: &str, values: &[f32], x: f32, y: &mut f32, color: Color) {
+    let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let title = match values.last() {
+        Some(last) => format!("{name}: {last:.2}  [{min:.2}, {max:.2}]"),
+        None => name.to_string(),
+    };
+    draw_label(&title, x, y, color);
+    draw_rectangle_lines(x, *y, WIDTH, PLOT_HEIGHT, 1.0, GRAY);
+    let range = (max - min).max(f32::EPSILON);
+    let to_screen = |i: usize, value: f32| {
+        vec2(
+            x + WIDTH * i as f32 / (HISTORY - 1) as f32,
+            *y + PLOT_HEIGHT * (1.0 - (value - min) / range),
+        )
+    };
+    for (i, pair) in values.windows(2).enumerate() {
+        let (start, end) = (to_screen(i, pair[0]), to_screen(i + 1, pair[1]));
+        draw_line(start.x, start.y, end.x, end.y, 1.0, color);
+    }
+    *y += PLOT_HEIGHT + ROW / 2.0;
+}
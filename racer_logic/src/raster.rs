@@ -0,0 +1,187 @@
+use macroquad::prelude::*;
+use std::f32::consts::FRAC_PI_2;
+
+/// RGBA image drawn in software, for exports that have no window to draw into. Shapes are given
+/// in world coordinates and mapped onto the image, which may be rotated against the world.
+#[derive(Debug, Clone)]
+pub struct Raster {
+    width: usize,
+    height: usize,
+    /// Row-major RGBA bytes, the first row is the top of the image.
+    pixels: Vec<u8>,
+    /// World point in the middle of the image.
+    center: Vec2,
+    /// Rotation from world to image directions.
+    rotation: Vec2,
+    /// Pixels per world unit.
+    scale: f32,
+}
+
+impl Raster {
+    /// Image of the world rectangle `view` with `scale` pixels per world unit.
+    pub fn new(view: Rect, scale: f32, background: Color) -> Self {
+        let width = (view.w * scale).ceil().max(1.0) as usize;
+        let height = (view.h * scale).ceil().max(1.0) as usize;
+        // the world direction pointing down the image is +y
+        Self::around(view.center(), -FRAC_PI_2, width, height, scale, background)
+    }
+
+    /// Image of `width` by `height` pixels centered on `center`, where the world direction
+    /// `heading` (an angle in radians) points up.
+    pub fn around(
+        center: Vec2,
+        heading: f32,
+        width: usize,
+        height: usize,
+        scale: f32,
+        background: Color,
+    ) -> Self {
+        let background: [u8; 4] = background.into();
+        Self {
+            width,
+            height,
+            pixels: background.repeat(width * height),
+            center,
+            rotation: Vec2::from_angle(-FRAC_PI_2 - heading),
+            scale,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Row-major RGBA bytes of the image.
+    pub fn rgba(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Row-major RGB bytes of the image, without the alpha channel.
+    pub fn rgb(&self) -> Vec<u8> {
+        self.pixels
+            .chunks_exact(4)
+            .flat_map(|pixel| &pixel[..3])
+            .copied()
+            .collect()
+    }
+
+    /// Smallest world rectangle containing the image.
+    pub fn view(&self) -> Rect {
+        let half_size = vec2(self.width as f32, self.height as f32) / (2.0 * self.scale);
+        // a rotated rectangle has the extents of its rotated half diagonals
+        let to_world = vec2(self.rotation.x, -self.rotation.y);
+        let extent = to_world
+            .rotate(half_size)
+            .abs()
+            .max(to_world.rotate(half_size * vec2(1.0, -1.0)).abs());
+        Rect::new(
+            self.center.x - extent.x,
+            self.center.y - extent.y,
+            2.0 * extent.x,
+            2.0 * extent.y,
+        )
+    }
+
+    fn to_pixel(&self, point: Vec2) -> Vec2 {
+        let size = vec2(self.width as f32, self.height as f32);
+        self.rotation.rotate(point - self.center) * self.scale + size / 2.0
+    }
+
+    /// Blends `color` over the pixel at column `x` and row `y` according to its alpha.
+    fn blend(&mut self, x: usize, y: usize, color: Color) {
+        let index = 4 * (y * self.width + x);
+        let pixel = &mut self.pixels[index..index + 4];
+        let source = [color.r, color.g, color.b];
+        for (channel, source) in pixel.iter_mut().zip(source) {
+            let blended = source * color.a + *channel as f32 / 255.0 * (1.0 - color.a);
+            *channel = (blended * 255.0).round() as u8;
+        }
+        pixel[3] = pixel[3].max((color.a * 255.0).round() as u8);
+    }
+
+    /// Range of pixel indices covering `[min, max]` in pixel coordinates, clipped to `size`.
+    fn span(min: f32, max: f32, size: usize) -> std::ops::Range<usize> {
+        let start = min.floor().max(0.0) as usize;
+        let end = (max.ceil().max(0.0) as usize).min(size);
+        start..end.max(start)
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect, color: Color) {
+        let (min, max) = (rect.point(), rect.point() + rect.size());
+        self.fill_polygon(&[min, vec2(max.x, min.y), max, vec2(min.x, max.y)], color);
+    }
+
+    /// Draws the segment from `start` to `end` with rounded ends, `width` in world units.
+    pub fn line(&mut self, start: Vec2, end: Vec2, width: f32, color: Color) {
+        let (start, end) = (self.to_pixel(start), self.to_pixel(end));
+        let radius = (width * self.scale / 2.0).max(0.5);
+        let (min, max) = (start.min(end) - radius, start.max(end) + radius);
+        let direction = end - start;
+        let length_squared = direction.length_squared().max(f32::EPSILON);
+        for y in Self::span(min.y, max.y, self.height) {
+            for x in Self::span(min.x, max.x, self.width) {
+                let center = vec2(x as f32 + 0.5, y as f32 + 0.5);
+                let along = ((center - start).dot(direction) / length_squared).clamp(0.0, 1.0);
+                if center.distance_squared(start + direction * along) <= radius * radius {
+                    self.blend(x, y, color);
+                }
+            }
+        }
+    }
+
+    /// Fills the polygon with the corners `points`, which may be concave. Pixels are filled when
+    /// their center is inside.
+    pub fn fill_polygon(&mut self, points: &[Vec2], color: Color) {
+        let points: Vec<Vec2> = points.iter().map(|&p| self.to_pixel(p)).collect();
+        let (min, max) = points.iter().fold((Vec2::MAX, Vec2::MIN), |(min, max), p| {
+            (min.min(*p), max.max(*p))
+        });
+        let mut crossings = vec![];
+        for y in Self::span(min.y, max.y, self.height) {
+            let center_y = y as f32 + 0.5;
+            crossings.clear();
+            for (i, &a) in points.iter().enumerate() {
+                let b = points[(i + 1) % points.len()];
+                // the same crossing whichever way the edge goes, so that polygons sharing an
+                // edge leave no gap between them
+                let (a, b) = if a.y <= b.y { (a, b) } else { (b, a) };
+                if (a.y <= center_y) != (b.y <= center_y) {
+                    crossings.push(a.x + (center_y - a.y) / (b.y - a.y) * (b.x - a.x));
+                }
+            }
+            crossings.sort_by(f32::total_cmp);
+            for pair in crossings.chunks_exact(2) {
+                // pixels whose center x + 0.5 lies in [pair[0], pair[1])
+                let (start, end) = ((pair[0] - 0.5).ceil(), (pair[1] - 0.5).ceil());
+                for x in Self::span(start, end, self.width) {
+                    self.blend(x, y, color);
+                }
+            }
+        }
+    }
+
+    /// Fills the rectangle of `size` centered on `center` and rotated by `rotation` radians.
+    pub fn fill_rotated_rect(&mut self, center: Vec2, size: Vec2, rotation: f32, color: Color) {
+        let rotation = Vec2::from_angle(rotation);
+        let corners = [
+            vec2(-0.5, -0.5),
+            vec2(0.5, -0.5),
+            vec2(0.5, 0.5),
+            vec2(-0.5, 0.5),
+        ]
+        .map(|corner| center + rotation.rotate(corner * size));
+        self.fill_polygon(&corners, color);
+    }
+
+    /// Draws the line through `points`. Overlapping joints are drawn twice, so transparent
+    /// colors darken there.
+    pub fn polyline(&mut self, points: &[Vec2], width: f32, color: Color) {
+        for pair in points.windows(2) {
+            self.line(pair[0], pair[1], width, color);
+        }
+    }
+}
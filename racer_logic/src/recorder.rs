@@ -0,0 +1,267 @@
+use crate::environment::{Action, Observation};
+use npyz::WriterBuilder;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// Columns written before and after the observation features.
+const LEADING_COLUMNS: [&str; 4] = ["episode", "seed", "step", "time"];
+const TRAILING_COLUMNS: [&str; 4] = ["target_steer", "target_throttle", "reward", "intervention"];
+
+enum Output {
+    Csv(BufWriter<File>),
+    /// NPZ archives cannot be appended to, rows are kept in memory and the whole archive is
+    /// rewritten at the end of each episode and when the recorder is dropped.
+    Npz(PathBuf, Vec<Vec<f64>>),
+}
+
+/// Records observations, actions and rewards of episodes into a dataset for behaviour cloning.
+///
+/// The format is chosen by the extension of the output file: `.csv` files have a header with
+/// the column names, `.npz` archives contain one array per column. Steps recorded after the
+/// last [`Recorder::end_episode`], e.g. of a race left by quitting the game, are written when
+/// the recorder is dropped.
+pub struct Recorder {
+    output: Output,
+    columns: Vec<String>,
+    episode: u64,
+    seed: u64,
+    step: u64,
+    /// Whether steps were recorded since the output was last written.
+    pending: bool,
+}
+
+impl Recorder {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let columns: Vec<String> = LEADING_COLUMNS
+            .into_iter()
+            .map(String::from)
+            .chain(Observation::feature_names())
+            .chain(TRAILING_COLUMNS.into_iter().map(String::from))
+            .collect();
+
+        let output = match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => {
+                let mut writer = BufWriter::new(File::create(path)?);
+                writeln!(writer, "{}", columns.join(","))?;
+                Output::Csv(writer)
+            }
+            Some("npz") => Output::Npz(path.to_owned(), vec![vec![]; columns.len()]),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unsupported recording format: {}", path.display()),
+                ));
+            }
+        };
+
+        Ok(Self {
+            output,
+            columns,
+            episode: 0,
+            seed: 0,
+            step: 0,
+            pending: false,
+        })
+    }
+
+    pub fn start_episode(&mut self, seed: u64) {
+        self.episode += 1;
+        self.seed = seed;
+        self.step = 0;
+    }
+
+    /// Records one step: the observation the action was chosen from and the reward it yielded.
+    /// `intervention` marks actions of a human correcting the controller.
+    pub fn record(
+        &mut self,
+        time: f64,
+        observation: &Observation,
+        action: &Action,
+        reward: f32,
+        intervention: bool,
+    ) -> io::Result<()> {
+        let mut values: Vec<f32> = observation.clone().into();
+        values.extend([action.steer, action.throttle, reward]);
+        values.push(if intervention { 1.0 } else { 0.0 });
+        let (episode, seed, step) = (self.episode, self.seed, self.step);
+        self.step += 1;
+        self.pending = true;
+
+        match &mut self.output {
+            Output::Csv(writer) => {
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                writeln!(
+                    writer,
+                    "{episode},{seed},{step},{time},{}",
+                    values.join(",")
+                )
+            }
+            Output::Npz(_, columns) => {
+                let row = [episode as f64, seed as f64, step as f64, time]
+                    .into_iter()
+                    .chain(values.into_iter().map(f64::from));
+                for (column, value) in columns.iter_mut().zip(row) {
+                    column.push(value);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Makes sure everything recorded so far is written to the output file.
+    pub fn end_episode(&mut self) -> io::Result<()> {
+        self.pending = false;
+        match &mut self.output {
+            Output::Csv(writer) => writer.flush(),
+            Output::Npz(path, columns) => {
+                let mut npz = npyz::npz::NpzWriter::create(path)?;
+                for (name, column) in self.columns.iter().zip(columns.iter()) {
+                    let mut writer = npz
+                        .array::<f64>(name, Default::default())?
+                        .default_dtype()
+                        .shape(&[column.len() as u64])
+                        .begin_nd()?;
+                    writer.extend(column.iter().copied())?;
+                    writer.finish()?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if self.pending
+            && let Err(e) = self.end_episode()
+        {
+            eprintln!("Recording failed: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Environment;
+    use std::fs;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("racer-recorder-{}-{name}", std::process::id()))
+    }
+
+    /// Records `steps` steps of an episode on `seed`, ending the episode if `end`.
+    fn record(recorder: &mut Recorder, seed: u64, steps: usize, end: bool) {
+        let mut environment = Environment::new(Some(seed));
+        let action = Action {
+            steer: 0.5,
+            throttle: 1.0,
+        };
+        recorder.start_episode(seed);
+        for step in 0..steps {
+            let observation = environment.observation.clone();
+            let outcome = environment.step(&action, true);
+            recorder
+                .record(
+                    step as f64 * 0.1,
+                    &observation,
+                    &action,
+                    outcome.reward,
+                    step == 1,
+                )
+                .unwrap();
+        }
+        if end {
+            recorder.end_episode().unwrap();
+        }
+    }
+
+    #[test]
+    fn csv_columns_match_the_training_script() {
+        // `observation_features` and the targets read by `research/train_reinforce.py`
+        let mut expected: Vec<String> = ["episode", "seed", "step", "time"]
+            .into_iter()
+            .chain([
+                "velocity",
+                "steering_angle",
+                "next_wp_angle",
+                "next_wp_dist",
+            ])
+            .map(String::from)
+            .collect();
+        expected.extend(
+            ["front_r", "front_l", "rear_r", "rear_l"].map(|w| format!("wheel_on_track_{w}")),
+        );
+        expected.extend((0..13).map(|i| format!("sensor_readings_{i}")));
+        expected.extend(
+            ["target_steer", "target_throttle", "reward", "intervention"].map(String::from),
+        );
+
+        let path = temp_path("columns.csv");
+        let mut recorder = Recorder::create(&path).unwrap();
+        record(&mut recorder, 3, 4, true);
+        record(&mut recorder, 5, 2, true);
+        let csv = fs::read_to_string(&path).unwrap();
+        drop(recorder);
+        fs::remove_file(&path).unwrap();
+
+        let mut lines = csv.lines();
+        assert_eq!(lines.next().unwrap(), expected.join(","));
+        let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+        assert_eq!(rows.len(), 6);
+        for row in &rows {
+            assert_eq!(row.len(), expected.len());
+        }
+        assert_eq!(rows[3][..3], ["1", "3", "3"]);
+        assert_eq!(rows[4][..3], ["2", "5", "0"]);
+        assert_eq!(rows[1][..2], ["1", "3"]);
+        let steer = expected.iter().position(|c| c == "target_steer").unwrap();
+        assert_eq!(rows[0][steer..steer + 2], ["0.5", "1"]);
+        assert_eq!(rows[0].last(), Some(&"0"));
+        assert_eq!(rows[1].last(), Some(&"1"));
+    }
+
+    #[test]
+    fn dropping_writes_pending_csv_rows() {
+        let path = temp_path("pending.csv");
+        let mut recorder = Recorder::create(&path).unwrap();
+        record(&mut recorder, 1, 3, false);
+        drop(recorder);
+        let csv = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(csv.lines().count(), 4);
+    }
+
+    #[test]
+    fn dropping_writes_pending_npz_rows() {
+        let path = temp_path("pending.npz");
+        let mut recorder = Recorder::create(&path).unwrap();
+        record(&mut recorder, 1, 3, true);
+        record(&mut recorder, 2, 2, false);
+        drop(recorder);
+        let mut npz = npyz::npz::NpzArchive::open(&path).unwrap();
+        let seeds: Vec<f64> = npz.by_name("seed").unwrap().unwrap().into_vec().unwrap();
+        let velocity: Vec<f64> = npz
+            .by_name("velocity")
+            .unwrap()
+            .unwrap()
+            .into_vec()
+            .unwrap();
+        drop(npz);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(seeds, [1.0, 1.0, 1.0, 2.0, 2.0]);
+        assert_eq!(velocity.len(), 5);
+    }
+
+    #[test]
+    fn unknown_formats_are_rejected() {
+        let path = temp_path("recording.txt");
+        let error = Recorder::create(&path).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(!path.exists());
+    }
+}
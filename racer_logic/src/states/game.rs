@@ -1,38 +1,52 @@
 use crate::{
-    car::Car,
-    controller::Controller,
-    environment::{Environment, Observation},
-    follow_camera::FollowCamera,
-    states::{State, finish::Finish},
+    environment::{Action, Environment, Observation, Outcome},
+    states::{Session, State, paused::Paused, results::Results},
+    trajectory::Trajectory,
     utils::format_time,
 };
 use macroquad::prelude::*;
-use std::iter::zip;
 
 pub struct Game {
-    follow_camera: FollowCamera,
+    session: Session,
     state_started: f64,
-    controller: Box<dyn Controller>,
     reward: f32,
+    /// Path driven in the current lap.
+    trajectory: Trajectory,
+    /// Times of the completed laps.
+    lap_times: Vec<f64>,
 }
 
 impl Game {
-    pub fn new(
-        follow_camera: &FollowCamera,
-        controller_factory: fn() -> Box<dyn Controller>,
-    ) -> Self {
-        let follow_camera = follow_camera.clone();
+    pub fn new(mut session: Session) -> Self {
+        session.policy_panel.clear();
+        session.controller.reset();
         Self {
-            follow_camera,
             state_started: get_time(),
-            controller: controller_factory(),
             reward: 0.0,
+            trajectory: Trajectory::default(),
+            lap_times: vec![],
+            session,
         }
     }
 
+    /// Leaves `duration` seconds spent paused out of the race time.
+    pub(super) fn skip_time(&mut self, duration: f64) {
+        self.state_started += duration;
+    }
+
+    /// Leaves the race before the finish.
+    pub(super) fn abandon(mut self) -> Session {
+        self.session.end_episode();
+        self.session
+    }
+
     fn draw_stopwatch(&self) {
         set_default_camera();
-        let stopwatch = format_time(self.current_time());
+        let mut stopwatch = format_time(self.current_time());
+        if self.session.laps > 1 {
+            let lap = self.lap_times.len() + 1;
+            stopwatch.push_str(&format!("  lap {lap}/{}", self.session.laps));
+        }
         draw_text(&stopwatch, 5.0, 24.0, 32.0, WHITE);
     }
 
@@ -40,53 +54,50 @@ impl Game {
         get_time() - self.state_started
     }
 
-    fn draw_observation(observation: &Observation, car: &Car, reward: f32) {
-        for (d, (start, end)) in zip(&observation.sensors.distances, &observation.sensors.rays) {
-            draw_line(start.x, start.y, end.x, end.y, 0.3, GREEN.with_alpha(0.2));
-            if let Some(d) = d {
-                let p = (*end - *start).normalize() * *d + *start;
-                draw_circle(p.x, p.y, 1.0, RED);
+    fn record(
+        &mut self,
+        observation: &Observation,
+        action: &Action,
+        outcome: &Outcome,
+    ) -> std::io::Result<()> {
+        let time = self.current_time();
+        let intervention = self.session.controller.intervention();
+        if let Some(recorder) = &mut self.session.recorder {
+            recorder.record(time, observation, action, outcome.reward, intervention)?;
+            if outcome.finished {
+                recorder.end_episode()?;
             }
         }
-
-        let to_waypoint = Vec2::from_angle(*car.rotation()).rotate(
-            Vec2::from_angle(observation.next_waypoint.angle) * observation.next_waypoint.distance,
-        );
-        let car_pos = car.windshield_position();
-        draw_line(
-            car_pos.x,
-            car_pos.y,
-            car_pos.x + to_waypoint.x,
-            car_pos.y + to_waypoint.y,
-            0.5,
-            GREEN.with_alpha(0.5),
-        );
-        push_camera_state();
-        set_default_camera();
-        draw_multiline_text(
-            &format!(
-                "next_waypoint: {:.2}\nspeed: {:.2}\nreward: {reward:.2}",
-                observation.next_waypoint.angle, observation.velocity
-            ),
-            screen_width() * 0.5,
-            screen_height() * 0.5,
-            16.0,
-            None,
-            YELLOW.with_alpha(0.3),
-        );
-        pop_camera_state();
+        Ok(())
     }
 }
 
 impl State for Game {
-    fn step(&mut self, environment: &mut Environment) -> Option<Box<dyn State>> {
-        //let mut vec: Vec<f32> = environment.observation.clone().into();
-        let action = self.controller.control(&environment.observation);
-        //vec.extend([action.steer, action.throttle]);
-        //println!("{vec:?}");
+    fn step(mut self: Box<Self>, environment: &mut Environment) -> Box<dyn State> {
+        self.session.debug.update();
+        self.session.policy_panel.update();
+        self.session.trajectories.update();
+        if is_key_pressed(KeyCode::P) || is_key_pressed(KeyCode::Escape) {
+            return Box::new(Paused::new(self));
+        }
 
+        let observation = environment.observation.clone();
+        let action = self.session.controller.control(environment);
         let outcome = environment.step(&action, false);
         self.reward += outcome.reward;
+        if self.trajectory.push(environment, &action) {
+            self.session
+                .trajectories
+                .add_off_track(*environment.car.position());
+        }
+        self.session
+            .policy_panel
+            .push(&action, outcome.reward, environment.observation.velocity);
+
+        if let Err(e) = self.record(&observation, &action, &outcome) {
+            eprintln!("Recording failed: {e}");
+            self.session.recorder = None;
+        }
 
         if is_key_pressed(KeyCode::Space) {
             let nearest_segment = &environment
@@ -99,19 +110,53 @@ impl State for Game {
             );
         }
 
-        if outcome.finished {
-            Some(Box::new(Finish::new(
-                &self.follow_camera,
-                self.current_time(),
-            )))
+        if !outcome.finished {
+            return self;
+        }
+        let time = self.current_time();
+        self.lap_times
+            .push(time - self.lap_times.iter().sum::<f64>());
+        if self.lap_times.len() < self.session.laps as usize {
+            // tracks are not closed, every lap starts over from the start
+            environment.restart();
+            self.trajectory.clear();
+            self.session.follow_camera.reset(&environment.car);
+            self.session.controller.reset();
+            if let Some(recorder) = &mut self.session.recorder {
+                recorder.start_episode(environment.seed);
+            }
+            self
         } else {
-            None
+            Box::new(Results::new(
+                self.session,
+                time,
+                self.reward,
+                self.lap_times,
+            ))
         }
     }
 
     fn draw(&mut self, environment: &Environment) {
-        environment.draw(&mut self.follow_camera);
-        Game::draw_observation(&environment.observation, &environment.car, self.reward);
+        environment.draw(&mut self.session.follow_camera);
+        let trajectories = &self.session.trajectories;
+        trajectories.draw_world(environment, &self.trajectory);
+        let debug = &self.session.debug;
+        debug.draw_world(environment, self.session.follow_camera.view());
+        debug.draw_screen(&environment.observation, self.reward);
+        trajectories.draw_screen();
         self.draw_stopwatch();
+        self.session.minimap.draw(&environment.car);
+        self.session
+            .policy_panel
+            .draw(self.session.controller.diagnostics());
+        if self.session.controller.intervention() {
+            draw_text(
+                "HUMAN DRIVING - press tab to hand back",
+                5.0,
+                48.0,
+                24.0,
+                ORANGE,
+            );
+        }
     }
 }
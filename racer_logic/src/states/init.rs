@@ -1,42 +1,87 @@
 use crate::{
     controller::Controller,
+    debug_overlay::DebugOverlay,
     environment::Environment,
-    follow_camera::FollowCamera,
-    states::{State, game::Game},
+    follow_camera::{CameraConfig, FollowCamera},
+    minimap::Minimap,
+    policy_panel::PolicyPanel,
+    recorder::Recorder,
+    states::{Session, State, game::Game},
+    trajectory_overlay::TrajectoryOverlay,
 };
 use macroquad::prelude::*;
 
 pub struct Init {
-    follow_camera: FollowCamera,
-    controller_factory: fn() -> Box<dyn Controller>,
+    session: Session,
 }
 
 impl Init {
-    pub fn new(environment: &Environment, controller_factory: fn() -> Box<dyn Controller>) -> Self {
-        let follow_camera = FollowCamera::new(&environment.car);
+    pub fn new(
+        environment: &Environment,
+        controller: Box<dyn Controller>,
+        recorder: Option<Recorder>,
+        camera: CameraConfig,
+        laps: u32,
+    ) -> Self {
         Self {
-            follow_camera,
-            controller_factory,
+            session: Session {
+                follow_camera: FollowCamera::new(&environment.car, camera),
+                minimap: Minimap::new(&environment.track),
+                debug: DebugOverlay::default(),
+                policy_panel: PolicyPanel::default(),
+                trajectories: TrajectoryOverlay::new(&environment.track),
+                controller,
+                recorder,
+                laps: laps.max(1),
+                best_time: None,
+            },
         }
     }
+
+    /// Waits for the start of a new episode in `environment`.
+    pub(super) fn restart(
+        mut session: Session,
+        environment: &Environment,
+        new_track: bool,
+    ) -> Self {
+        session.restart(environment, new_track);
+        Self { session }
+    }
 }
 
 impl State for Init {
-    fn step(&mut self, _environment: &mut Environment) -> Option<Box<dyn State>> {
+    fn step(mut self: Box<Self>, environment: &mut Environment) -> Box<dyn State> {
         if is_key_pressed(KeyCode::Space) {
-            Some(Box::new(Game::new(
-                &self.follow_camera,
-                self.controller_factory,
-            )))
+            if let Some(recorder) = &mut self.session.recorder {
+                recorder.start_episode(environment.seed);
+            }
+            Box::new(Game::new(self.session))
         } else {
-            None
+            self
         }
     }
 
     fn draw(&mut self, environment: &Environment) {
-        environment.draw(&mut self.follow_camera);
+        let session = &mut self.session;
+        environment.draw(&mut session.follow_camera);
 
+        session.minimap.draw(&environment.car);
         set_default_camera();
         draw_text("Press space to start", 5.0, 24.0, 32.0, WHITE);
+        let camera = session.follow_camera.mode().name();
+        draw_text(
+            &format!("C: camera ({camera}), mouse wheel: zoom, M: minimap, P: pause"),
+            5.0,
+            48.0,
+            24.0,
+            WHITE,
+        );
+        draw_text(
+            "I: policy panel, T: racing line, H: heatmap, F1-F6: debug",
+            5.0,
+            72.0,
+            24.0,
+            WHITE,
+        );
     }
 }
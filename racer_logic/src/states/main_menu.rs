@@ -0,0 +1,221 @@
+use crate::{
+    car::CarSpec,
+    controller::{Controller, ControllerFactory},
+    environment::Environment,
+    follow_camera::CameraConfig,
+    recorder::Recorder,
+    states::{Init, State, menu::Menu},
+    track::Track,
+};
+use macroquad::prelude::*;
+use std::path::PathBuf;
+
+const TRACK: usize = 0;
+const CAR: usize = 1;
+const CONTROLLER: usize = 2;
+const LAPS: usize = 3;
+const START: usize = 4;
+const MAX_LAPS: u32 = 10;
+
+/// Controller the player can pick in the [`MainMenu`].
+#[derive(Clone)]
+pub struct ControllerChoice {
+    pub name: String,
+    pub factory: ControllerFactory,
+}
+
+/// Track the races are driven on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrackChoice {
+    /// A new random seed for every start from the menu.
+    Random,
+    Seed(u64),
+    File(PathBuf),
+}
+
+/// Choices offered by the [`MainMenu`] and the ones selected at first.
+pub struct MenuOptions {
+    pub controllers: Vec<ControllerChoice>,
+    /// Index of the selected controller.
+    pub controller: usize,
+    /// Track files to choose from besides generated tracks.
+    pub track_files: Vec<PathBuf>,
+    pub track: TrackChoice,
+    /// One of [`CarSpec::PRESETS`].
+    pub car: String,
+    pub laps: u32,
+    pub recorder: Option<Recorder>,
+    pub camera: CameraConfig,
+}
+
+/// Picks the track, car, controller and number of laps before the first race.
+pub struct MainMenu {
+    options: MenuOptions,
+    /// Index into random, seed and the track files.
+    track: usize,
+    seed: u64,
+    car: usize,
+    menu: Menu,
+    /// Why the last start failed.
+    error: Option<String>,
+}
+
+impl MainMenu {
+    pub fn new(options: MenuOptions) -> Self {
+        let (track, seed) = match &options.track {
+            TrackChoice::Random => (0, 0),
+            TrackChoice::Seed(seed) => (1, *seed),
+            TrackChoice::File(path) => (
+                2 + options
+                    .track_files
+                    .iter()
+                    .position(|file| file == path)
+                    .unwrap_or_default(),
+                0,
+            ),
+        };
+        let car = CarSpec::PRESETS
+            .iter()
+            .position(|&name| name == options.car)
+            .unwrap_or_default();
+        let mut menu = Self {
+            options,
+            track,
+            seed,
+            car,
+            menu: Menu::new(&[]),
+            error: None,
+        };
+        menu.update_labels();
+        menu
+    }
+
+    fn track_choice(&self) -> TrackChoice {
+        match self.track {
+            0 => TrackChoice::Random,
+            1 => TrackChoice::Seed(self.seed),
+            i => TrackChoice::File(self.options.track_files[i - 2].clone()),
+        }
+    }
+
+    fn update_labels(&mut self) {
+        let track = match self.track_choice() {
+            TrackChoice::Random => "random".to_owned(),
+            TrackChoice::Seed(seed) => format!("seed {seed}"),
+            TrackChoice::File(path) => path.display().to_string(),
+        };
+        let controller = self
+            .options
+            .controllers
+            .get(self.options.controller)
+            .map_or("-", |choice| &choice.name);
+        self.menu.set_items(vec![
+            format!("Track: {track}"),
+            format!("Car: {}", CarSpec::PRESETS[self.car]),
+            format!("Controller: {controller}"),
+            format!("Laps: {}", self.options.laps),
+            "Start".to_owned(),
+        ]);
+    }
+
+    /// Moves the value of the selected row `step` choices forward or back.
+    fn change(&mut self, step: isize) {
+        let cycle = |value: usize, count: usize| {
+            (value as isize + step).rem_euclid(count as isize) as usize
+        };
+        match self.menu.selected() {
+            TRACK => self.track = cycle(self.track, 2 + self.options.track_files.len()),
+            CAR => self.car = cycle(self.car, CarSpec::PRESETS.len()),
+            CONTROLLER => {
+                let count = self.options.controllers.len().max(1);
+                self.options.controller = cycle(self.options.controller, count);
+            }
+            LAPS => {
+                self.options.laps =
+                    (self.options.laps as isize + step).clamp(1, MAX_LAPS as isize) as u32
+            }
+            _ => {}
+        }
+    }
+
+    /// Types digits of the seed while it is selected.
+    fn edit_seed(&mut self) {
+        let editing = self.menu.selected() == TRACK && self.track == 1;
+        // drain the typed characters either way, they would pile up otherwise
+        while let Some(c) = get_char_pressed() {
+            if let Some(digit) = c.to_digit(10).filter(|_| editing) {
+                self.seed = self.seed.saturating_mul(10).saturating_add(digit.into());
+            }
+        }
+        if editing && is_key_pressed(KeyCode::Backspace) {
+            self.seed /= 10;
+        }
+    }
+
+    /// Environment and controller of the selected options.
+    fn start(&self) -> Result<(Environment, Box<dyn Controller>), String> {
+        let environment = match self.track_choice() {
+            TrackChoice::Random => Environment::new(None),
+            TrackChoice::Seed(seed) => Environment::new(Some(seed)),
+            TrackChoice::File(path) => Track::load(&path)
+                .map(|track| Environment::with_track(track, 0))
+                .map_err(|e| format!("Failed to load {}: {e}", path.display()))?,
+        };
+        let spec = CarSpec::preset(CarSpec::PRESETS[self.car]).unwrap_or_default();
+        let choice = self
+            .options
+            .controllers
+            .get(self.options.controller)
+            .ok_or("No controller to choose from")?;
+        let controller = (choice.factory)()?;
+        Ok((environment.with_car(spec), controller))
+    }
+}
+
+impl State for MainMenu {
+    fn step(mut self: Box<Self>, environment: &mut Environment) -> Box<dyn State> {
+        self.edit_seed();
+        if is_key_pressed(KeyCode::Right) {
+            self.change(1);
+        }
+        if is_key_pressed(KeyCode::Left) {
+            self.change(-1);
+        }
+        let chosen = self.menu.update();
+        self.update_labels();
+        match chosen {
+            Some(START) => match self.start() {
+                Ok((mut started, controller)) => {
+                    started.car.share_texture(&environment.car);
+                    *environment = started;
+                    let MenuOptions {
+                        recorder,
+                        camera,
+                        laps,
+                        ..
+                    } = self.options;
+                    Box::new(Init::new(environment, controller, recorder, camera, laps))
+                }
+                Err(e) => {
+                    eprintln!("{e}");
+                    self.error = Some(e);
+                    self
+                }
+            },
+            Some(_) => {
+                self.change(1);
+                self.update_labels();
+                self
+            }
+            None => self,
+        }
+    }
+
+    fn draw(&mut self, _environment: &Environment) {
+        clear_background(DARKGREEN);
+        let mut details =
+            vec!["up/down: select, left/right: change, digits: seed, enter: start".to_owned()];
+        details.extend(self.error.iter().flat_map(|e| e.lines().map(String::from)));
+        self.menu.draw("RACER", &details);
+    }
+}
@@ -0,0 +1,70 @@
+use macroquad::prelude::*;
+
+/// Vertical list of items chosen with the arrow keys and enter.
+pub struct Menu {
+    items: Vec<String>,
+    selected: usize,
+}
+
+impl Menu {
+    pub fn new(items: &[&str]) -> Self {
+        Self {
+            items: items.iter().map(|item| item.to_string()).collect(),
+            selected: 0,
+        }
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Replaces the labels of the items, e.g. to show the values they are set to.
+    pub fn set_items(&mut self, items: Vec<String>) {
+        self.items = items;
+        self.selected = self.selected.min(self.items.len().saturating_sub(1));
+    }
+
+    /// Moves the selection and returns the index of the item chosen this frame.
+    pub fn update(&mut self) -> Option<usize> {
+        let count = self.items.len();
+        if is_key_pressed(KeyCode::Down) {
+            self.selected = (self.selected + 1) % count;
+        }
+        if is_key_pressed(KeyCode::Up) {
+            self.selected = (self.selected + count - 1) % count;
+        }
+        is_key_pressed(KeyCode::Enter).then_some(self.selected)
+    }
+
+    /// Draws `title`, the `details` below it and the items centered on a dimmed screen.
+    pub fn draw(&self, title: &str, details: &[String]) {
+        set_default_camera();
+        draw_rectangle(
+            0.0,
+            0.0,
+            screen_width(),
+            screen_height(),
+            BLACK.with_alpha(0.6),
+        );
+        let center = screen_width() / 2.0;
+        let mut y = screen_height() / 3.0;
+        let size = measure_text(title, None, 48, 1.0);
+        draw_text(title, center - size.width / 2.0, y, 48.0, WHITE);
+        for detail in details {
+            y += 32.0;
+            let size = measure_text(detail, None, 24, 1.0);
+            draw_text(detail, center - size.width / 2.0, y, 24.0, LIGHTGRAY);
+        }
+        y += 16.0;
+        for (i, item) in self.items.iter().enumerate() {
+            y += 40.0;
+            let (text, color) = if i == self.selected {
+                (format!("> {item} <"), YELLOW)
+            } else {
+                (item.clone(), WHITE)
+            };
+            let size = measure_text(&text, None, 32, 1.0);
+            draw_text(&text, center - size.width / 2.0, y, 32.0, color);
+        }
+    }
+}
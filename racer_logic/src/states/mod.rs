@@ -1,11 +1,59 @@
-use crate::environment::Environment;
-mod finish;
+use crate::{
+    controller::Controller, debug_overlay::DebugOverlay, environment::Environment,
+    follow_camera::FollowCamera, minimap::Minimap, policy_panel::PolicyPanel, recorder::Recorder,
+    trajectory_overlay::TrajectoryOverlay,
+};
 mod game;
 mod init;
+mod main_menu;
+mod menu;
+mod paused;
+mod results;
 
 pub use init::Init;
+pub use main_menu::{ControllerChoice, MainMenu, MenuOptions, TrackChoice};
 
 pub trait State {
-    fn step(&mut self, environment: &mut Environment) -> Option<Box<dyn State>>;
+    /// Advances the state by one frame and returns the state of the next frame, `self` unless
+    /// there is a transition.
+    fn step(self: Box<Self>, environment: &mut Environment) -> Box<dyn State>;
     fn draw(&mut self, environment: &Environment);
 }
+
+/// Everything carried from one state to the next.
+struct Session {
+    follow_camera: FollowCamera,
+    minimap: Minimap,
+    debug: DebugOverlay,
+    policy_panel: PolicyPanel,
+    trajectories: TrajectoryOverlay,
+    /// Drives every race of the session, reset at the start of each one.
+    controller: Box<dyn Controller>,
+    recorder: Option<Recorder>,
+    /// Runs of the track in one race.
+    laps: u32,
+    /// Fastest finish on the current track.
+    best_time: Option<f64>,
+}
+
+impl Session {
+    /// Follows the car of a new episode, `new_track` tells that the track changed too.
+    fn restart(&mut self, environment: &Environment, new_track: bool) {
+        self.follow_camera.reset(&environment.car);
+        if new_track {
+            self.minimap = Minimap::new(&environment.track);
+            self.trajectories = TrajectoryOverlay::new(&environment.track);
+            self.best_time = None;
+        }
+    }
+
+    /// Ends the recorded episode, e.g. when leaving a race before the finish.
+    fn end_episode(&mut self) {
+        if let Some(recorder) = &mut self.recorder
+            && let Err(e) = recorder.end_episode()
+        {
+            eprintln!("Recording failed: {e}");
+            self.recorder = None;
+        }
+    }
+}
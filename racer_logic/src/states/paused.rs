@@ -0,0 +1,56 @@
+use crate::{
+    environment::Environment,
+    states::{State, game::Game, init::Init, menu::Menu},
+};
+use macroquad::prelude::*;
+
+const RESUME: usize = 0;
+const RESTART: usize = 1;
+const NEW_TRACK: usize = 2;
+
+/// Race on hold, resumed with `P` or escape.
+pub struct Paused {
+    game: Box<Game>,
+    /// Time up to which the pause was left out of the race time.
+    since: f64,
+    menu: Menu,
+}
+
+impl Paused {
+    pub fn new(game: Box<Game>) -> Self {
+        Self {
+            game,
+            since: get_time(),
+            menu: Menu::new(&["Resume", "Restart", "New track"]),
+        }
+    }
+}
+
+impl State for Paused {
+    fn step(mut self: Box<Self>, environment: &mut Environment) -> Box<dyn State> {
+        let now = get_time();
+        self.game.skip_time(now - self.since);
+        self.since = now;
+
+        if is_key_pressed(KeyCode::P) || is_key_pressed(KeyCode::Escape) {
+            return self.game;
+        }
+        match self.menu.update() {
+            Some(RESUME) => self.game,
+            Some(RESTART) => {
+                environment.restart();
+                Box::new(Init::restart(self.game.abandon(), environment, false))
+            }
+            Some(NEW_TRACK) => {
+                environment.new_track();
+                Box::new(Init::restart(self.game.abandon(), environment, true))
+            }
+            _ => self,
+        }
+    }
+
+    fn draw(&mut self, environment: &Environment) {
+        self.game.draw(environment);
+        self.menu.draw("PAUSED", &[]);
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{
+    environment::Environment,
+    states::{Session, State, init::Init, menu::Menu},
+    utils::format_time,
+};
+
+const TRY_AGAIN: usize = 0;
+const NEW_TRACK: usize = 1;
+
+/// Race time and reward after crossing the finish line.
+pub struct Results {
+    session: Session,
+    time: f64,
+    reward: f32,
+    lap_times: Vec<f64>,
+    /// Whether `time` beat all earlier finishes on this track.
+    best: bool,
+    menu: Menu,
+}
+
+impl Results {
+    pub fn new(mut session: Session, time: f64, reward: f32, lap_times: Vec<f64>) -> Self {
+        let best = session.best_time.is_none_or(|best| time < best);
+        if best {
+            session.best_time = Some(time);
+        }
+        Self {
+            session,
+            time,
+            reward,
+            lap_times,
+            best,
+            menu: Menu::new(&["Try again", "New track"]),
+        }
+    }
+}
+
+impl State for Results {
+    fn step(mut self: Box<Self>, environment: &mut Environment) -> Box<dyn State> {
+        match self.menu.update() {
+            Some(TRY_AGAIN) => {
+                environment.restart();
+                Box::new(Init::restart(self.session, environment, false))
+            }
+            Some(NEW_TRACK) => {
+                environment.new_track();
+                Box::new(Init::restart(self.session, environment, true))
+            }
+            _ => self,
+        }
+    }
+
+    fn draw(&mut self, environment: &Environment) {
+        environment.draw(&mut self.session.follow_camera);
+        self.session.minimap.draw(&environment.car);
+
+        let best = match (self.best, self.session.best_time) {
+            (true, _) => "new best time!".to_owned(),
+            (false, Some(best)) => format!("best: {}", format_time(best)),
+            (false, None) => String::new(),
+        };
+        let mut details = vec![best, format!("reward: {:.1}", self.reward)];
+        if self.lap_times.len() > 1 {
+            let laps: Vec<String> = self.lap_times.iter().map(|&t| format_time(t)).collect();
+            details.push(format!("laps: {}", laps.join(", ")));
+        }
+        self.menu
+            .draw(&format!("FINISH: {}", format_time(self.time)), &details);
+    }
+}
@@ -0,0 +1,122 @@
+use super::{Shape, Straight, TRACK_WIDTH, Track, Turn, TurnType};
+use std::{fs, io, path::Path};
+
+/// Plain text track files with one shape per line:
+///
+/// ```text
+/// straight <length>
+/// left <radius> <degrees>
+/// right <radius> <degrees>
+/// finish <length>
+/// ```
+///
+/// Empty lines and lines starting with `#` are ignored, the finish has to be the last shape.
+/// Lengths, radii and degrees are positive, radii at least half the track width.
+impl Track {
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut text = String::from("# racer track\n");
+        for shape in self.shapes() {
+            let line = match shape {
+                Shape::Straight(Straight {
+                    length,
+                    is_finish: false,
+                }) => format!("straight {length}"),
+                Shape::Straight(Straight {
+                    length,
+                    is_finish: true,
+                }) => format!("finish {length}"),
+                Shape::Turn(Turn {
+                    radius,
+                    deg,
+                    turn_type,
+                }) => {
+                    let direction = match turn_type {
+                        TurnType::Left => "left",
+                        TurnType::Right => "right",
+                    };
+                    format!("{direction} {radius} {deg}")
+                }
+            };
+            text.push_str(&line);
+            text.push('\n');
+        }
+        fs::write(path, text)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let invalid = |line: usize, message: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {message}", line + 1),
+            )
+        };
+
+        let mut shapes = vec![];
+        let mut lines = vec![];
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let numbers = fields[1..]
+                .iter()
+                .map(|field| field.parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| invalid(number, &e.to_string()))?;
+            if let Some(value) = numbers.iter().find(|n| !(n.is_finite() && **n > 0.0)) {
+                return Err(invalid(
+                    number,
+                    &format!("expected a finite positive number, found {value}"),
+                ));
+            }
+            if let ("left" | "right", &[radius, _]) = (fields[0], &numbers[..])
+                && radius < TRACK_WIDTH / 2.0
+            {
+                return Err(invalid(
+                    number,
+                    &format!("radius {radius} is less than half the track width"),
+                ));
+            }
+            let shape = match (fields[0], &numbers[..]) {
+                ("straight", &[length]) => Shape::Straight(Straight {
+                    length,
+                    is_finish: false,
+                }),
+                ("finish", &[length]) => Shape::Straight(Straight {
+                    length,
+                    is_finish: true,
+                }),
+                ("left", &[radius, deg]) => Shape::Turn(Turn {
+                    radius,
+                    deg,
+                    turn_type: TurnType::Left,
+                }),
+                ("right", &[radius, deg]) => Shape::Turn(Turn {
+                    radius,
+                    deg,
+                    turn_type: TurnType::Right,
+                }),
+                _ => return Err(invalid(number, &format!("unknown shape '{line}'"))),
+            };
+            shapes.push(shape);
+            lines.push(number);
+        }
+
+        let ends_with_finish = matches!(
+            shapes.last(),
+            Some(Shape::Straight(Straight {
+                is_finish: true,
+                ..
+            }))
+        );
+        if !ends_with_finish {
+            return Err(invalid(
+                lines.last().copied().unwrap_or(0),
+                "the track has to end with a finish straight",
+            ));
+        }
+        Track::from_shapes(shapes).map_err(|index| invalid(lines[index], "shape after the finish"))
+    }
+}
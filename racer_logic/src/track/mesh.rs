@@ -0,0 +1,306 @@
+use super::constant::TRACK_WIDTH;
+use super::segment::{Segment, TRACK_COLOR};
+use super::shape::Shape;
+use crate::physics::RotRect;
+use crate::raster::Raster;
+use macroquad::{models::Vertex, prelude::*};
+
+/// Largest distance between a turn and the polygon approximating it, in world units.
+const TOLERANCE: f32 = 0.05;
+const EDGE_WIDTH: f32 = 1.0;
+const KERB_WIDTH: f32 = 2.5;
+/// Length of a kerb block along the centerline.
+const KERB_LENGTH: f32 = 4.0;
+/// Squares of the finish checkerboard across and along the track.
+const CHECKERBOARD: (usize, usize) = (12, 6);
+/// Quads per mesh, so that their indices stay below the 5000 macroquad draws in one call by
+/// default. Consecutive meshes are still batched into as few draw calls as fit.
+const MAX_QUADS: usize = (5000 - 1) / 6;
+
+/// Triangles of the whole track: asphalt, edge lines, kerbs on the turns and the checkered
+/// finish, split into meshes of at most [`MAX_QUADS`] quads.
+pub struct TrackMesh {
+    meshes: Vec<Mesh>,
+    /// Number of quads of the asphalt, the first ones of the mesh.
+    asphalt: usize,
+}
+
+impl TrackMesh {
+    pub fn new(segments: &[impl AsRef<Segment>], finish: Option<&RotRect>) -> Self {
+        let mut mesh = Self {
+            meshes: vec![],
+            asphalt: 0,
+        };
+        let half_width = TRACK_WIDTH / 2.0;
+        let sections = sections(segments.iter().map(AsRef::as_ref));
+        mesh.strip(&sections, -half_width, half_width, TRACK_COLOR);
+        mesh.asphalt = sections.len().saturating_sub(1);
+
+        for segment in segments.iter().map(AsRef::as_ref) {
+            if let Shape::Turn(_) = segment.shape {
+                mesh.kerbs(segment);
+            }
+        }
+
+        for side in [-1.0, 1.0] {
+            let (inner, outer) = (half_width - EDGE_WIDTH / 2.0, half_width + EDGE_WIDTH / 2.0);
+            mesh.strip(&sections, side * inner, side * outer, WHITE);
+        }
+
+        if let Some(finish) = finish {
+            mesh.checkerboard(finish);
+        }
+        mesh
+    }
+
+    pub fn draw(&self) {
+        for mesh in &self.meshes {
+            draw_mesh(mesh);
+        }
+    }
+
+    /// Draws the quads visible in the view of `raster` without a window.
+    pub fn raster(&self, raster: &mut Raster) {
+        let view = raster.view();
+        for (corners, color) in self.quads() {
+            if overlaps(view, &corners) {
+                raster.fill_polygon(&corners, color);
+            }
+        }
+    }
+
+    /// Fills the asphalt visible in the view of `raster` with `color`, without the markings.
+    pub fn raster_asphalt(&self, raster: &mut Raster, color: Color) {
+        let view = raster.view();
+        for (corners, _) in self.quads().take(self.asphalt) {
+            if overlaps(view, &corners) {
+                raster.fill_polygon(&corners, color);
+            }
+        }
+    }
+
+    /// Corners and color of every quad, in the order they are drawn.
+    fn quads(&self) -> impl Iterator<Item = ([Vec2; 4], Color)> + '_ {
+        self.meshes
+            .iter()
+            .flat_map(|mesh| mesh.vertices.chunks_exact(4))
+            .map(|quad| {
+                let [r, g, b, a] = quad[0].color;
+                let corners = [0, 1, 2, 3].map(|i| quad[i].position.truncate());
+                (corners, Color::from_rgba(r, g, b, a))
+            })
+    }
+
+    /// Adds the quadrilateral with the corners `corners` in order around it.
+    fn quad(&mut self, corners: [Vec2; 4], color: Color) {
+        let mesh = match self.meshes.last_mut() {
+            Some(mesh) if mesh.vertices.len() < 4 * MAX_QUADS => mesh,
+            _ => {
+                self.meshes.push(Mesh {
+                    vertices: vec![],
+                    indices: vec![],
+                    texture: None,
+                });
+                self.meshes.last_mut().unwrap()
+            }
+        };
+        let first = mesh.vertices.len() as u16;
+        mesh.vertices
+            .extend(corners.map(|corner| Vertex::new(corner.x, corner.y, 0.0, 0.0, 0.0, color)));
+        mesh.indices
+            .extend([0, 1, 2, 0, 2, 3].map(|index| first + index));
+    }
+
+    /// Band between the signed offsets `from` and `to` to the left of the centerline, through
+    /// consecutive `sections`.
+    fn strip(&mut self, sections: &[(Vec2, Vec2)], from: f32, to: f32, color: Color) {
+        for pair in sections.windows(2) {
+            let [(a, a_left), (b, b_left)] = [pair[0], pair[1]];
+            self.quad(
+                [
+                    a + a_left * from,
+                    b + b_left * from,
+                    b + b_left * to,
+                    a + a_left * to,
+                ],
+                color,
+            );
+        }
+    }
+
+    /// Red and white blocks outside both edges of a turn.
+    fn kerbs(&mut self, segment: &Segment) {
+        let blocks = (segment.length() / KERB_LENGTH).ceil().max(1.0) as usize;
+        let along = |block: usize| segment.length() * block as f32 / blocks as f32;
+        let from = TRACK_WIDTH / 2.0 + EDGE_WIDTH / 2.0;
+        let to = from + KERB_WIDTH;
+        for block in 0..blocks {
+            let color = if block % 2 == 0 { RED } else { WHITE };
+            let ends = [along(block), along(block + 1)].map(|along| {
+                let waypoint = segment.waypoint_at(along);
+                (waypoint.pos, waypoint.dir.perp())
+            });
+            for side in [-1.0, 1.0] {
+                self.strip(&ends, side * from, side * to, color);
+            }
+        }
+    }
+
+    /// Black and white squares filling the finish area.
+    fn checkerboard(&mut self, finish: &RotRect) {
+        let [c0, c1, c2, c3] = finish.outline();
+        // bilinear interpolation between the corners, `u` along c0 -> c1, `v` along c0 -> c3
+        let point = |u: f32, v: f32| c0.lerp(c1, u).lerp(c3.lerp(c2, u), v);
+        let (columns, rows) = CHECKERBOARD;
+        for column in 0..columns {
+            for row in 0..rows {
+                let color = if (column + row) % 2 == 0 {
+                    WHITE
+                } else {
+                    BLACK
+                };
+                let (u0, u1) = (column as f32, column as f32 + 1.0);
+                let (v0, v1) = (row as f32, row as f32 + 1.0);
+                let (u0, u1) = (u0 / columns as f32, u1 / columns as f32);
+                let (v0, v1) = (v0 / rows as f32, v1 / rows as f32);
+                self.quad(
+                    [point(u0, v0), point(u1, v0), point(u1, v1), point(u0, v1)],
+                    color,
+                );
+            }
+        }
+    }
+}
+
+/// Points of the centerline and their left normals, from the start of the first segment to
+/// the end of the last one. Straights need only their ends, turns get more points the sharper
+/// they are so that the polygon stays within [`TOLERANCE`] of the outer kerb.
+fn sections<'a>(segments: impl Iterator<Item = &'a Segment>) -> Vec<(Vec2, Vec2)> {
+    let mut sections = vec![];
+    for segment in segments {
+        let steps = match &segment.shape {
+            Shape::Straight(_) => 1,
+            Shape::Turn(turn) => {
+                let outer = turn.radius + TRACK_WIDTH / 2.0 + EDGE_WIDTH / 2.0 + KERB_WIDTH;
+                let step = 2.0 * (1.0 - TOLERANCE / outer).acos();
+                (turn.deg.to_radians() / step).ceil().max(1.0) as usize
+            }
+        };
+        // the first point is the last point of the previous segment
+        let first = if sections.is_empty() { 0 } else { 1 };
+        for i in first..=steps {
+            let waypoint = segment.waypoint_at(segment.length() * i as f32 / steps as f32);
+            sections.push((waypoint.pos, waypoint.dir.perp()));
+        }
+    }
+    sections
+}
+
+/// Whether the bounding box of `corners` overlaps `view`.
+fn overlaps(view: Rect, corners: &[Vec2]) -> bool {
+    let (min, max) = corners
+        .iter()
+        .fold((Vec2::MAX, Vec2::MIN), |(min, max), corner| {
+            (min.min(*corner), max.max(*corner))
+        });
+    view.overlaps(&Rect::new(min.x, min.y, max.x - min.x, max.y - min.y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::track::shape::{Straight, Turn, TurnType, Waypoint};
+    use std::rc::Rc;
+
+    /// Vertices and indices macroquad draws in one call by default, it clamps geometry reaching
+    /// either of them.
+    const MAX_DRAW_VERTICES: usize = 10000;
+    const MAX_DRAW_INDICES: usize = 5000;
+
+    fn turn(radius: f32, deg: f32, turn_type: TurnType) -> Shape {
+        Shape::Turn(Turn {
+            radius,
+            deg,
+            turn_type,
+        })
+    }
+
+    /// Segments with the given shapes, each starting at the end of the previous one.
+    fn chain(shapes: impl IntoIterator<Item = Shape>) -> Vec<Rc<Segment>> {
+        let mut segments: Vec<Rc<Segment>> = vec![];
+        for shape in shapes {
+            let (start, distance) = match segments.last() {
+                Some(last) => (last.end.clone(), last.distance + last.length()),
+                None => (Waypoint::default(), 0.0),
+            };
+            segments.push(Rc::new(Segment::new(start, shape, distance)));
+        }
+        segments
+    }
+
+    #[test]
+    fn arc_vertices_lie_on_the_track_edges() {
+        let (radius, half_width) = (40.0, TRACK_WIDTH / 2.0);
+        let segments = chain([turn(radius, 120.0, TurnType::Left)]);
+        let center = match &segments[0].shape {
+            Shape::Turn(turn) => turn.center(&segments[0].start),
+            Shape::Straight(_) => unreachable!(),
+        };
+        let mesh = TrackMesh::new(&segments, None);
+        assert!(mesh.asphalt > 1);
+        for (corners, _) in mesh.quads().take(mesh.asphalt) {
+            for corner in corners {
+                let distance = corner.distance(center);
+                let edge = [radius - half_width, radius + half_width]
+                    .into_iter()
+                    .map(|edge| (distance - edge).abs())
+                    .fold(f32::INFINITY, f32::min);
+                assert!(edge < 1e-3, "{corner} is {distance} from the center");
+            }
+        }
+    }
+
+    #[test]
+    fn arcs_stay_within_the_tolerance() {
+        let segments = chain([turn(15.0, 180.0, TurnType::Right)]);
+        let sections = sections(segments.iter().map(AsRef::as_ref));
+        let outer = 15.0 + TRACK_WIDTH / 2.0 + EDGE_WIDTH / 2.0 + KERB_WIDTH;
+        for pair in sections.windows(2) {
+            let angle = pair[0].1.angle_between(pair[1].1).abs();
+            let sagitta = outer * (1.0 - (angle / 2.0).cos());
+            assert!(sagitta <= TOLERANCE + 1e-4, "{sagitta}");
+        }
+    }
+
+    #[test]
+    fn meshes_fit_in_one_draw_call() {
+        let shapes = (0..400).flat_map(|i| {
+            let turn_type = if i % 2 == 0 {
+                TurnType::Left
+            } else {
+                TurnType::Right
+            };
+            [
+                turn(12.0, 150.0, turn_type),
+                Shape::Straight(Straight {
+                    length: 20.0,
+                    is_finish: false,
+                }),
+            ]
+        });
+        let segments = chain(shapes);
+        let finish = RotRect::new(vec2(0.0, 0.0), vec2(TRACK_WIDTH, 10.0), 0.0);
+        let mesh = TrackMesh::new(&segments, Some(&finish));
+        assert!(mesh.meshes.len() > 1);
+        for mesh in &mesh.meshes {
+            assert!(mesh.vertices.len() < MAX_DRAW_VERTICES);
+            assert!(mesh.indices.len() < MAX_DRAW_INDICES);
+            assert_eq!(mesh.indices.len(), mesh.vertices.len() / 4 * 6);
+            assert!(
+                mesh.indices
+                    .iter()
+                    .all(|&i| (i as usize) < mesh.vertices.len())
+            );
+        }
+    }
+}
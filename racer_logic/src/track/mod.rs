@@ -1,7 +1,12 @@
 mod constant;
+mod file;
+mod mesh;
 mod segment;
 mod shape;
 #[allow(clippy::module_inception)]
 mod track;
 
+pub use constant::TRACK_WIDTH;
+pub use segment::Segment;
+pub use shape::{Shape, Straight, Turn, TurnType, Waypoint};
 pub use track::{Track, sensor_readings};
@@ -2,16 +2,19 @@ use super::constant::*;
 use super::shape::*;
 use crate::physics::point_in_angle;
 use macroquad::prelude::*;
-use std::f32::consts::FRAC_PI_2;
+
+pub const TRACK_COLOR: Color = Color::new(32.0 / 255.0, 32.0 / 255.0, 32.0 / 255.0, 1.0);
 
 pub struct Segment {
     pub start: Waypoint,
     pub shape: Shape,
     pub end: Waypoint,
+    /// Distance along the centerline from the start of the track to the start of this segment.
+    pub distance: f32,
 }
 
 impl Segment {
-    pub fn new(start: Waypoint, shape: Shape) -> Self {
+    pub fn new(start: Waypoint, shape: Shape, distance: f32) -> Self {
         let end = match shape {
             Shape::Straight(ref straight) => Waypoint {
                 pos: start.pos + start.dir * straight.length,
@@ -31,80 +34,73 @@ impl Segment {
                 }
             }
         };
-        Self { start, shape, end }
+        Self {
+            start,
+            shape,
+            end,
+            distance,
+        }
+    }
+
+    pub fn length(&self) -> f32 {
+        match &self.shape {
+            Shape::Straight(straight) => straight.length,
+            Shape::Turn(turn) => turn.radius * turn.deg.to_radians(),
+        }
     }
 
-    pub fn draw(&self) {
-        let track_color = Color::from_rgba(32, 32, 32, 255);
-        match self.shape {
-            Shape::Straight(ref straight) => {
-                for (d, color, thickness) in [
-                    (0.0, track_color, TRACK_WIDTH),
-                    (-TRACK_WIDTH / 2.0, WHITE, 1.0),
-                    (TRACK_WIDTH / 2.0, WHITE, 1.0),
-                ] {
-                    let shift = if d != 0.0 {
-                        (self.end.pos - self.start.pos).perp().normalize() * d
-                    } else {
-                        Vec2::ZERO
-                    };
-                    draw_line(
-                        self.start.pos.x + shift.x,
-                        self.start.pos.y + shift.y,
-                        self.end.pos.x + shift.x,
-                        self.end.pos.y + shift.y,
-                        thickness,
-                        color,
-                    );
+    /// Signed curvature of the centerline, positive for left turns.
+    pub fn curvature(&self) -> f32 {
+        match &self.shape {
+            Shape::Straight(_) => 0.0,
+            Shape::Turn(turn) => match turn.turn_type {
+                TurnType::Left => 1.0 / turn.radius,
+                TurnType::Right => -1.0 / turn.radius,
+            },
+        }
+    }
 
-                    if straight.is_finish {
-                        let middle = self.start.pos.midpoint(self.end.pos);
-                        let orientation = self.end.pos - self.start.pos;
-                        draw_rectangle_ex(
-                            middle.x,
-                            middle.y,
-                            TRACK_WIDTH,
-                            20.0,
-                            DrawRectangleParams {
-                                offset: vec2(0.5, 0.5),
-                                rotation: orientation.to_angle() - FRAC_PI_2,
-                                color: WHITE,
-                            },
-                        );
-                    }
+    /// Point and direction of the centerline after travelling `along` from the segment start.
+    pub fn waypoint_at(&self, along: f32) -> Waypoint {
+        match &self.shape {
+            Shape::Straight(_) => Waypoint {
+                pos: self.start.pos + self.start.dir * along,
+                dir: self.start.dir,
+            },
+            Shape::Turn(turn) => {
+                let center = turn.center(&self.start);
+                let rot_vector = Vec2::from_angle(self.curvature() * along);
+                Waypoint {
+                    pos: rot_vector.rotate(self.start.pos - center) + center,
+                    dir: rot_vector.rotate(self.start.dir),
                 }
             }
-            Shape::Turn(ref turn) => {
-                let center = turn.center(&self.start);
-                let to_start = self.start.pos - center;
+        }
+    }
 
-                // draw_circle_lines(center.x, center.y, 3.0, 1.0, GREEN);
-                let start_deg = to_start.to_angle().to_degrees();
-                let arc_start = match turn.turn_type {
-                    TurnType::Left => start_deg,
-                    TurnType::Right => start_deg - turn.deg,
+    /// Projects `pos` on the centerline of the segment. Returns the distance travelled along the
+    /// segment and the projected point.
+    pub fn project(&self, pos: &Vec2) -> (f32, Vec2) {
+        match &self.shape {
+            Shape::Straight(straight) => {
+                let along = (*pos - self.start.pos)
+                    .dot(self.start.dir)
+                    .clamp(0.0, straight.length);
+                (along, self.start.pos + self.start.dir * along)
+            }
+            Shape::Turn(turn) => {
+                let sgn = match turn.turn_type {
+                    TurnType::Left => 1.0,
+                    TurnType::Right => -1.0,
                 };
-                for (d, color, thickness) in [
-                    (-TRACK_WIDTH / 2.0, track_color, TRACK_WIDTH),
-                    (-TRACK_WIDTH / 2.0 - 0.5, WHITE, 1.0),
-                    (TRACK_WIDTH / 2.0 - 0.5, WHITE, 1.0),
-                ] {
-                    draw_arc(
-                        center.x,
-                        center.y,
-                        32,
-                        turn.radius + d,
-                        arc_start,
-                        thickness,
-                        turn.deg,
-                        color,
-                    );
-                }
+                let center = turn.center(&self.start);
+                let to_start = self.start.pos - center;
+                let angle =
+                    (sgn * to_start.angle_between(*pos - center)).clamp(0.0, turn.deg.to_radians());
+                let point = Vec2::from_angle(sgn * angle).rotate(to_start) + center;
+                (angle * turn.radius, point)
             }
         }
-
-        // self.start.draw();
-        // end.draw();
     }
 
     pub fn bbox(&self) -> rstar::AABB<[f32; 2]> {
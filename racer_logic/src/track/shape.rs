@@ -7,8 +7,8 @@ pub struct Waypoint {
 }
 
 impl Waypoint {
-    #[allow(dead_code)]
-    fn draw(&self) {
+    /// Draws the position and the direction of the waypoint.
+    pub fn draw(&self) {
         draw_circle_lines(self.pos.x, self.pos.y, 5.0, 1.0, YELLOW);
         let end = self.pos + (self.dir * 20.0);
         draw_line(self.pos.x, self.pos.y, end.x, end.y, 1.0, YELLOW);
@@ -1,22 +1,26 @@
+use super::mesh::TrackMesh;
 use super::segment::*;
 use super::shape::*;
-use crate::car::Car;
 use crate::physics::RotRect;
 use crate::physics::arc_vs_segment;
 use crate::physics::segment_vs_segment;
+use crate::raster::Raster;
 use crate::track::constant::TRACK_WIDTH;
 use macroquad::prelude::*;
 use macroquad::rand::{gen_range, rand};
 use std::f32::consts::FRAC_PI_2;
-use std::rc::Rc;
+use std::{cell::OnceCell, rc::Rc};
 
 type TreeNode =
     rstar::primitives::GeomWithData<rstar::primitives::Rectangle<[f32; 2]>, Rc<Segment>>;
 
+#[derive(Clone)]
 pub struct Track {
     segments: Vec<Rc<Segment>>,
     rtree: Option<rstar::RTree<TreeNode>>,
     finish: Option<RotRect>,
+    /// Built when first drawn, shared by clones.
+    mesh: Rc<OnceCell<TrackMesh>>,
 }
 
 impl Track {
@@ -25,6 +29,7 @@ impl Track {
             segments: vec![],
             rtree: None,
             finish: None,
+            mesh: Rc::default(),
         };
         track.add_shape(Shape::Straight(Straight {
             length: 100.0,
@@ -33,17 +38,80 @@ impl Track {
         track
     }
 
-    pub fn draw(&self, car: &Car) {
-        if let Some(rtree) = &self.rtree {
-            let pos = car.position();
-            let view = Rect::new(pos.x - 300.0, pos.y - 200.0, 600.0, 400.0);
-            //draw_rectangle_lines(view.x, view.y, view.w, view.h, 3.0, WHITE);
-            let envelope =
-                rstar::AABB::from_corners([view.x, view.y], [view.x + view.w, view.y + view.h]);
-            rtree
-                .locate_in_envelope_intersecting(&envelope)
-                .for_each(|segment| segment.data.draw());
+    /// Track made of `shapes`, a finish straight has to be the last one. Fails with the index of
+    /// the first shape after the finish if there is one.
+    pub fn from_shapes(shapes: impl IntoIterator<Item = Shape>) -> Result<Self, usize> {
+        let mut track = Self {
+            segments: vec![],
+            rtree: None,
+            finish: None,
+            mesh: Rc::default(),
+        };
+        for (index, shape) in shapes.into_iter().enumerate() {
+            if track.finish.is_some() {
+                return Err(index);
+            }
+            match shape {
+                Shape::Straight(Straight {
+                    length,
+                    is_finish: true,
+                }) => track.add_finish_straight(length),
+                shape => track.add_shape(shape),
+            }
         }
+        track.compute_rtree();
+        Ok(track)
+    }
+
+    pub fn shapes(&self) -> impl Iterator<Item = &Shape> {
+        self.segments.iter().map(|segment| &segment.shape)
+    }
+
+    /// Draws the whole track from its mesh, built on the first call.
+    pub fn draw(&self) {
+        self.mesh().draw();
+    }
+
+    /// Draws the track visible in the view of `raster` without a window, from the same mesh as
+    /// [`Track::draw`].
+    pub fn raster(&self, raster: &mut Raster) {
+        self.mesh().raster(raster);
+    }
+
+    /// Fills the track surface visible in the view of `raster` with `color`, without the
+    /// markings drawn on and next to it.
+    pub fn raster_surface(&self, raster: &mut Raster, color: Color) {
+        self.mesh().raster_asphalt(raster, color);
+    }
+
+    fn mesh(&self) -> &TrackMesh {
+        self.mesh
+            .get_or_init(|| TrackMesh::new(&self.segments, self.finish.as_ref()))
+    }
+
+    /// Segments whose bounding boxes are within reach of `view`.
+    pub fn segments_in(&self, view: Rect) -> Vec<&Segment> {
+        let Some(rtree) = &self.rtree else {
+            return vec![];
+        };
+        // segment boxes only span their end points, turns bulge out of them by less than the
+        // diameter of their circle
+        let radius = self
+            .shapes()
+            .filter_map(|shape| match shape {
+                Shape::Turn(turn) => Some(turn.radius),
+                Shape::Straight(_) => None,
+            })
+            .fold(0.0, f32::max);
+        let margin = 2.0 * radius + TRACK_WIDTH / 2.0;
+        let envelope = rstar::AABB::from_corners(
+            [view.x - margin, view.y - margin],
+            [view.right() + margin, view.bottom() + margin],
+        );
+        rtree
+            .locate_in_envelope_intersecting(&envelope)
+            .map(|segment| segment.data.as_ref())
+            .collect()
     }
 
     pub fn on_track(&self, pos: &Vec2) -> bool {
@@ -68,6 +136,11 @@ impl Track {
             .collect()
     }
 
+    /// Area the car has to touch to finish.
+    pub fn finish_area(&self) -> Option<&RotRect> {
+        self.finish.as_ref()
+    }
+
     pub fn finish(&self, car_bbox: &RotRect) -> bool {
         if let Some(finish) = &self.finish {
             finish.collide(car_bbox)
@@ -76,6 +149,70 @@ impl Track {
         }
     }
 
+    /// Distance travelled along the centerline when the car is at `pos`.
+    pub fn progress(&self, pos: &Vec2) -> f32 {
+        self.nearest_segments(pos, 3)
+            .iter()
+            .map(|segment| {
+                let (along, closest) = segment.project(pos);
+                (segment.distance + along, closest.distance_squared(*pos))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(progress, _)| progress)
+            .unwrap_or(0.0)
+    }
+
+    /// Length of the centerline from the start to the end of the finish straight.
+    pub fn length(&self) -> f32 {
+        self.segments
+            .last()
+            .map(|last| last.distance + last.length())
+            .unwrap_or(0.0)
+    }
+
+    /// Distance from the start to the beginning of the finish straight.
+    pub fn finish_distance(&self) -> Option<f32> {
+        self.finish.as_ref()?;
+        self.segments.last().map(|last| last.distance)
+    }
+
+    /// Points of the centerline every `spacing` units, from the start to the end of the finish
+    /// straight.
+    pub fn centerline(&self, spacing: f32) -> Vec<Vec2> {
+        let length = self.length();
+        let count = (length / spacing).ceil() as usize;
+        (0..=count)
+            .map(|i| self.waypoint_at((i as f32 * spacing).min(length)).pos)
+            .collect()
+    }
+
+    /// Smallest rectangle containing the whole track.
+    pub fn bounds(&self) -> Rect {
+        let points = self.centerline(5.0);
+        let min = points.iter().fold(Vec2::MAX, |min, p| min.min(*p));
+        let max = points.iter().fold(Vec2::MIN, |max, p| max.max(*p));
+        let (min, max) = (min - TRACK_WIDTH / 2.0, max + TRACK_WIDTH / 2.0);
+        Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
+
+    fn segment_at(&self, distance: f32) -> &Segment {
+        let index = self
+            .segments
+            .partition_point(|segment| segment.distance <= distance);
+        &self.segments[index.saturating_sub(1)]
+    }
+
+    /// Point and direction of the centerline at `distance` from the start.
+    pub fn waypoint_at(&self, distance: f32) -> Waypoint {
+        let segment = self.segment_at(distance);
+        segment.waypoint_at((distance - segment.distance).clamp(0.0, segment.length()))
+    }
+
+    /// Signed curvature of the centerline at `distance` from the start.
+    pub fn curvature_at(&self, distance: f32) -> f32 {
+        self.segment_at(distance).curvature()
+    }
+
     fn last_end(&self) -> Waypoint {
         self.segments
             .last()
@@ -84,8 +221,10 @@ impl Track {
     }
 
     fn add_shape(&mut self, shape: Shape) {
+        self.mesh = Rc::default();
+        let distance = self.length();
         self.segments
-            .push(Rc::new(Segment::new(self.last_end(), shape)));
+            .push(Rc::new(Segment::new(self.last_end(), shape, distance)));
     }
 
     pub fn add_random_shape(&mut self) {
@@ -135,8 +274,12 @@ impl Track {
     }
 
     pub fn add_finish(&mut self) {
+        self.add_finish_straight(100.0);
+    }
+
+    fn add_finish_straight(&mut self, length: f32) {
         self.add_shape(Shape::Straight(Straight {
-            length: 100.0,
+            length,
             is_finish: true,
         }));
 
@@ -0,0 +1,348 @@
+use crate::{
+    environment::{Action, Environment},
+    raster::Raster,
+    track::{TRACK_WIDTH, Track},
+    utils::parse_name,
+};
+use macroquad::prelude::*;
+use std::{fmt::Write, str::FromStr};
+
+/// State of the car after one step.
+#[derive(Debug, Clone, Copy)]
+pub struct TrajectoryPoint {
+    pub position: Vec2,
+    pub velocity: f32,
+    pub steer: f32,
+    pub throttle: f32,
+}
+
+/// Path driven in one episode, with the places where the car left the track.
+#[derive(Debug, Clone)]
+pub struct Trajectory {
+    points: Vec<TrajectoryPoint>,
+    off_track: Vec<Vec2>,
+    /// Whether all wheels were on the track after the last step.
+    on_track: bool,
+}
+
+impl Default for Trajectory {
+    fn default() -> Self {
+        Self {
+            points: vec![],
+            off_track: vec![],
+            on_track: true,
+        }
+    }
+}
+
+impl Trajectory {
+    /// Records the state of `environment` after a step taken with `action`. Returns whether the
+    /// car just left the track, that is one of its wheels did.
+    pub fn push(&mut self, environment: &Environment, action: &Action) -> bool {
+        let position = *environment.car.position();
+        self.points.push(TrajectoryPoint {
+            position,
+            velocity: *environment.car.velocity(),
+            steer: action.steer,
+            throttle: action.throttle,
+        });
+        let was_on_track = self.on_track;
+        self.on_track = !environment.observation.wheels_on_track.contains(&false);
+        let left = was_on_track && !self.on_track;
+        if left {
+            self.off_track.push(position);
+        }
+        left
+    }
+
+    pub fn points(&self) -> &[TrajectoryPoint] {
+        &self.points
+    }
+
+    /// Positions of the car when it left the track.
+    pub fn off_track_events(&self) -> &[Vec2] {
+        &self.off_track
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Draws the path in world coordinates, colored by `line`.
+    pub fn draw(&self, line: LineColor, width: f32) {
+        for pair in self.points.windows(2) {
+            let (start, end) = (pair[0].position, pair[1].position);
+            draw_line(start.x, start.y, end.x, end.y, width, line.color(&pair[1]));
+        }
+    }
+}
+
+/// What the color of the racing line shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineQuantity {
+    /// From blue when standing to red at `top_speed`.
+    #[default]
+    Speed,
+    /// Blue when braking, white when coasting and red at full throttle.
+    Throttle,
+    /// Blue when steering right, white when going straight and red when steering left.
+    Steer,
+}
+
+impl LineQuantity {
+    pub const ALL: [LineQuantity; 3] = [
+        LineQuantity::Speed,
+        LineQuantity::Throttle,
+        LineQuantity::Steer,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LineQuantity::Speed => "speed",
+            LineQuantity::Throttle => "throttle",
+            LineQuantity::Steer => "steer",
+        }
+    }
+}
+
+impl FromStr for LineQuantity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_name(s, "line color", &Self::ALL, Self::name)
+    }
+}
+
+/// Coloring of a racing line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineColor {
+    pub quantity: LineQuantity,
+    /// Speed drawn in the hottest color, see [`crate::car::CarSpec::top_speed`].
+    pub top_speed: f32,
+}
+
+impl LineColor {
+    pub fn color(&self, point: &TrajectoryPoint) -> Color {
+        match self.quantity {
+            LineQuantity::Speed => sequential(point.velocity / self.top_speed.max(f32::EPSILON)),
+            LineQuantity::Throttle => diverging(point.throttle),
+            LineQuantity::Steer => diverging(point.steer),
+        }
+    }
+}
+
+/// Blue, cyan, green, yellow to red for `value` between zero and one.
+fn sequential(value: f32) -> Color {
+    const STOPS: [Color; 5] = [BLUE, SKYBLUE, GREEN, YELLOW, RED];
+    let position = value.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let index = (position as usize).min(STOPS.len() - 2);
+    mix(STOPS[index], STOPS[index + 1], position - index as f32)
+}
+
+/// Blue for -1, white for 0 and red for 1.
+fn diverging(value: f32) -> Color {
+    let value = value.clamp(-1.0, 1.0);
+    if value < 0.0 {
+        mix(WHITE, BLUE, -value)
+    } else {
+        mix(WHITE, RED, value)
+    }
+}
+
+fn mix(a: Color, b: Color, t: f32) -> Color {
+    Color::from_vec(a.to_vec().lerp(b.to_vec(), t))
+}
+
+/// Number of off-track events in each cell of a grid over a track.
+#[derive(Debug, Clone)]
+pub struct Heatmap {
+    bounds: Rect,
+    /// Side of the cells in world units.
+    cell: f32,
+    columns: usize,
+    counts: Vec<u32>,
+}
+
+impl Heatmap {
+    pub fn new(bounds: Rect, cell: f32) -> Self {
+        let columns = (bounds.w / cell).ceil().max(1.0) as usize;
+        let rows = (bounds.h / cell).ceil().max(1.0) as usize;
+        Self {
+            bounds,
+            cell,
+            columns,
+            counts: vec![0; columns * rows],
+        }
+    }
+
+    /// Counts an event at `position`, events outside of the bounds are dropped.
+    pub fn add(&mut self, position: Vec2) {
+        let cell = (position - self.bounds.point()) / self.cell;
+        if cell.x < 0.0 || cell.y < 0.0 || cell.x as usize >= self.columns {
+            return;
+        }
+        let index = cell.y as usize * self.columns + cell.x as usize;
+        if let Some(count) = self.counts.get_mut(index) {
+            *count += 1;
+        }
+    }
+
+    pub fn add_trajectory(&mut self, trajectory: &Trajectory) {
+        for &position in trajectory.off_track_events() {
+            self.add(position);
+        }
+    }
+
+    pub fn total(&self) -> u32 {
+        self.counts.iter().sum()
+    }
+
+    /// Cells with events and their count relative to the busiest cell.
+    pub fn cells(&self) -> impl Iterator<Item = (Rect, f32)> + '_ {
+        let max = self.counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(move |(index, &count)| {
+                let (column, row) = (index % self.columns, index / self.columns);
+                let rect = Rect::new(
+                    self.bounds.x + column as f32 * self.cell,
+                    self.bounds.y + row as f32 * self.cell,
+                    self.cell,
+                    self.cell,
+                );
+                (rect, count as f32 / max)
+            })
+    }
+
+    /// Draws the cells in world coordinates.
+    pub fn draw(&self) {
+        for (rect, intensity) in self.cells() {
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, heat(intensity));
+        }
+    }
+}
+
+/// Color of a heatmap cell with `intensity` between zero and one.
+fn heat(intensity: f32) -> Color {
+    MAGENTA.with_alpha(0.2 + 0.6 * intensity)
+}
+
+/// Racing lines of episodes on one track over their off-track heatmap, exported as SVG or as
+/// an image.
+pub struct TrajectoryPlot<'a> {
+    pub track: &'a Track,
+    pub trajectories: &'a [Trajectory],
+    pub heatmap: &'a Heatmap,
+    pub line: LineColor,
+}
+
+impl TrajectoryPlot<'_> {
+    /// Width of the racing lines in world units.
+    const LINE_WIDTH: f32 = 1.5;
+    /// Steps drawn as one SVG polyline of a single color.
+    const SVG_STEPS: usize = 10;
+
+    fn view(&self) -> Rect {
+        let margin = TRACK_WIDTH;
+        let bounds = self.track.bounds();
+        Rect::new(
+            bounds.x - margin,
+            bounds.y - margin,
+            bounds.w + 2.0 * margin,
+            bounds.h + 2.0 * margin,
+        )
+    }
+
+    /// End points of the finish line, across the start of the finish straight.
+    fn finish_line(&self) -> Option<(Vec2, Vec2)> {
+        let waypoint = self.track.waypoint_at(self.track.finish_distance()?);
+        let across = waypoint.dir.perp() * TRACK_WIDTH / 2.0;
+        Some((waypoint.pos - across, waypoint.pos + across))
+    }
+
+    /// Draws the plot with `scale` pixels per world unit.
+    pub fn raster(&self, scale: f32) -> Raster {
+        let mut raster = Raster::new(self.view(), scale, DARKGREEN);
+        self.track.raster(&mut raster);
+        for (rect, intensity) in self.heatmap.cells() {
+            raster.fill_rect(rect, heat(intensity));
+        }
+        for trajectory in self.trajectories {
+            for pair in trajectory.points().windows(2) {
+                let color = self.line.color(&pair[1]);
+                raster.line(pair[0].position, pair[1].position, Self::LINE_WIDTH, color);
+            }
+        }
+        raster
+    }
+
+    /// The plot as an SVG document in world units.
+    pub fn svg(&self) -> String {
+        let view = self.view();
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n",
+            view.x, view.y, view.w, view.h
+        );
+        let _ = writeln!(svg, "{}", rect(view, DARKGREEN));
+        let centerline = svg_points(self.track.centerline(2.0).into_iter());
+        for (width, color) in [(TRACK_WIDTH + 2.0, WHITE), (TRACK_WIDTH, DARKGRAY)] {
+            let _ = writeln!(
+                svg,
+                "<polyline points=\"{centerline}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{width}\" stroke-linejoin=\"round\"/>",
+                hex(color)
+            );
+        }
+        if let Some((start, end)) = self.finish_line() {
+            let _ = writeln!(
+                svg,
+                "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#ffffff\" stroke-width=\"2\"/>",
+                start.x, start.y, end.x, end.y
+            );
+        }
+        for (cell, intensity) in self.heatmap.cells() {
+            let _ = writeln!(svg, "{}", rect(cell, heat(intensity)));
+        }
+        for trajectory in self.trajectories {
+            let points = trajectory.points();
+            for start in (0..points.len().saturating_sub(1)).step_by(Self::SVG_STEPS) {
+                // chunks share their end points to keep the line connected
+                let chunk = &points[start..(start + Self::SVG_STEPS + 1).min(points.len())];
+                let _ = writeln!(
+                    svg,
+                    "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"round\"/>",
+                    svg_points(chunk.iter().map(|point| point.position)),
+                    hex(self.line.color(&chunk[chunk.len() / 2])),
+                    Self::LINE_WIDTH
+                );
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+fn svg_points(points: impl Iterator<Item = Vec2>) -> String {
+    points
+        .map(|p| format!("{:.1},{:.1}", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn hex(color: Color) -> String {
+    let [r, g, b, _]: [u8; 4] = color.into();
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+fn rect(rect: Rect, color: Color) -> String {
+    format!(
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" fill-opacity=\"{:.2}\"/>",
+        rect.x,
+        rect.y,
+        rect.w,
+        rect.h,
+        hex(color),
+        color.a
+    )
+}
@@ -0,0 +1,84 @@
+use crate::{
+    environment::Environment,
+    track::Track,
+    trajectory::{Heatmap, LineColor, LineQuantity, Trajectory},
+};
+use macroquad::prelude::*;
+
+/// Side of the heatmap cells in world units.
+const HEATMAP_CELL: f32 = 10.0;
+
+/// Racing line of the current race over the places where the car left the track in all races
+/// on the track.
+pub struct TrajectoryOverlay {
+    /// Cycled with `T`, `None` hides the line.
+    line: Option<LineQuantity>,
+    /// Toggled with `H`.
+    heatmap_visible: bool,
+    heatmap: Heatmap,
+}
+
+impl TrajectoryOverlay {
+    pub fn new(track: &Track) -> Self {
+        Self {
+            line: Some(LineQuantity::Speed),
+            heatmap_visible: true,
+            heatmap: Heatmap::new(track.bounds(), HEATMAP_CELL),
+        }
+    }
+
+    /// Changes the layers whose keys were pressed.
+    pub fn update(&mut self) {
+        if is_key_pressed(KeyCode::T) {
+            self.line = match self.line {
+                None => Some(LineQuantity::ALL[0]),
+                Some(quantity) => LineQuantity::ALL
+                    .into_iter()
+                    .skip_while(|q| *q != quantity)
+                    .nth(1),
+            };
+        }
+        if is_key_pressed(KeyCode::H) {
+            self.heatmap_visible = !self.heatmap_visible;
+        }
+    }
+
+    /// Counts an off-track event at `position`.
+    pub fn add_off_track(&mut self, position: Vec2) {
+        self.heatmap.add(position);
+    }
+
+    /// Draws the heatmap and `trajectory` in world coordinates.
+    pub fn draw_world(&self, environment: &Environment, trajectory: &Trajectory) {
+        if self.heatmap_visible {
+            self.heatmap.draw();
+        }
+        if let Some(quantity) = self.line {
+            let line = LineColor {
+                quantity,
+                top_speed: environment.car.spec().top_speed(),
+            };
+            trajectory.draw(line, 1.0);
+        }
+    }
+
+    /// Draws what the layers show in screen coordinates.
+    pub fn draw_screen(&self) {
+        push_camera_state();
+        set_default_camera();
+        let line = self.line.map_or("off", LineQuantity::name);
+        let heatmap = if self.heatmap_visible {
+            format!("{} off-track events", self.heatmap.total())
+        } else {
+            "off".to_owned()
+        };
+        draw_text(
+            &format!("T line: {line}, H heatmap: {heatmap}"),
+            5.0,
+            screen_height() - 28.0,
+            16.0,
+            GRAY,
+        );
+        pop_camera_state();
+    }
+}
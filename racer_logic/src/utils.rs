@@ -5,3 +5,50 @@ pub fn format_time(time: f64) -> String {
     let minutes = time / 6000;
     format!("{minutes:02}:{seconds:02}:{hundrets:02}")
 }
+
+/// The value among `all` called `s`, or an error naming the `kind` of value and listing the
+/// names it could have been.
+pub(crate) fn parse_name<T: Copy>(
+    s: &str,
+    kind: &str,
+    all: &[T],
+    name: fn(T) -> &'static str,
+) -> Result<T, String> {
+    all.iter()
+        .copied()
+        .find(|&value| name(value) == s)
+        .ok_or_else(|| {
+            let names: Vec<&str> = all.iter().map(|&value| name(value)).collect();
+            format!(
+                "unknown {kind} '{s}', expected one of: {}",
+                names.join(", ")
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::follow_camera::CameraMode;
+
+    #[test]
+    fn names_round_trip() {
+        for mode in CameraMode::ALL {
+            assert_eq!(
+                parse_name(mode.name(), "mode", &CameraMode::ALL, CameraMode::name),
+                Ok(mode)
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_names_list_the_expected_ones() {
+        assert_eq!(
+            "orbit".parse::<CameraMode>(),
+            Err(
+                "unknown camera mode 'orbit', expected one of: chase, north-up, overview, free"
+                    .to_owned()
+            )
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use macroquad::rand::RandGenerator;
+use racer_logic::{
+    car::Car,
+    controller::Controller,
+    environment::{Action, Environment},
+    track::Track,
+};
+use std::f32::consts::TAU;
+
+/// Tuning parameters of the [`MpcController`].
+#[derive(Debug, Clone)]
+pub struct MpcConfig {
+    /// Number of actions in the planned sequence, at least one.
+    pub horizon: usize,
+    /// Number of simulation steps each planned action is held for.
+    pub action_repeat: usize,
+    /// Number of action sequences sampled around the current plan.
+    pub samples: usize,
+    /// Standard deviation of the noise added to the plan when sampling.
+    pub noise: f32,
+    /// Temperature of the exponential weighting, relative to the spread of the sample costs.
+    pub temperature: f32,
+    /// Cost of one wheel being off the track for one simulation step.
+    pub off_track_penalty: f32,
+    pub seed: u64,
+}
+
+impl Default for MpcConfig {
+    fn default() -> Self {
+        Self {
+            horizon: 12,
+            action_repeat: 5,
+            samples: 64,
+            noise: 0.6,
+            temperature: 0.1,
+            off_track_penalty: 2.0,
+            seed: 0,
+        }
+    }
+}
+
+/// Model-predictive controller using model predictive path integral (MPPI) control.
+///
+/// Every `action_repeat` steps the controller samples noisy variations of its current plan,
+/// rolls a copy of the car forward on the track for each of them and replaces the plan with the
+/// average of the samples weighted by how far along the track they got.
+pub struct MpcController {
+    config: MpcConfig,
+    plan: Vec<(f32, f32)>,
+    rng: RandGenerator,
+    step: usize,
+}
+
+impl MpcController {
+    pub fn new(mut config: MpcConfig) -> Self {
+        config.horizon = config.horizon.max(1);
+        let rng = RandGenerator::new();
+        rng.srand(config.seed);
+        Self {
+            plan: vec![(0.0, 1.0); config.horizon],
+            config,
+            rng,
+            step: 0,
+        }
+    }
+
+    fn gaussian(&self) -> f32 {
+        // Box-Muller transform
+        let u1 = self.rng.gen_range(f32::EPSILON, 1.0);
+        let u2 = self.rng.gen_range(0.0, 1.0);
+        (-2.0 * u1.ln()).sqrt() * (TAU * u2).cos()
+    }
+
+    fn perturb(&self, plan: &[(f32, f32)]) -> Vec<(f32, f32)> {
+        plan.iter()
+            .map(|&(steer, throttle)| {
+                (
+                    (steer + self.config.noise * self.gaussian()).clamp(-1.0, 1.0),
+                    (throttle + self.config.noise * self.gaussian()).clamp(-1.0, 1.0),
+                )
+            })
+            .collect()
+    }
+
+    fn rollout(&self, car: &Car, track: &Track, plan: &[(f32, f32)]) -> f32 {
+        let mut car = car.clone();
+        let start = track.progress(car.position());
+        let mut penalty = 0.0;
+        for &(steer, throttle) in plan {
+            for _ in 0..self.config.action_repeat {
+                let wheels_on_track = car.wheels_on_track(track);
+                let off_track = wheels_on_track.iter().filter(|&&w| !w).count();
+                penalty += off_track as f32 * self.config.off_track_penalty;
+                car.update(&wheels_on_track, steer, throttle, true);
+                if track.finish(car.bbox()) {
+                    return penalty - (track.length() - start);
+                }
+            }
+        }
+        penalty - (track.progress(car.position()) - start)
+    }
+
+    fn optimize(&mut self, environment: &Environment) {
+        // the unperturbed plan is always one of the candidates
+        let candidates: Vec<_> = (0..self.config.samples.max(1))
+            .map(|i| {
+                if i == 0 {
+                    self.plan.clone()
+                } else {
+                    self.perturb(&self.plan)
+                }
+            })
+            .collect();
+        let costs: Vec<f32> = candidates
+            .iter()
+            .map(|plan| self.rollout(&environment.car, &environment.track, plan))
+            .collect();
+
+        let min = costs.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = costs.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let scale = self.config.temperature * (max - min).max(1e-6);
+        let weights: Vec<f32> = costs.iter().map(|c| (-(c - min) / scale).exp()).collect();
+        let total: f32 = weights.iter().sum();
+
+        for (i, action) in self.plan.iter_mut().enumerate() {
+            let (steer, throttle) = candidates
+                .iter()
+                .zip(&weights)
+                .fold((0.0, 0.0), |(steer, throttle), (plan, w)| {
+                    (steer + plan[i].0 * w, throttle + plan[i].1 * w)
+                });
+            *action = (steer / total, throttle / total);
+        }
+    }
+}
+
+impl Controller for MpcController {
+    fn control(&mut self, environment: &Environment) -> Action {
+        if self.step.is_multiple_of(self.config.action_repeat.max(1)) {
+            if self.step > 0 {
+                // the first planned action has been executed, warm start from the rest
+                self.plan.rotate_left(1);
+                if let Some(&second_last) = self.plan.iter().rev().nth(1) {
+                    *self.plan.last_mut().unwrap() = second_last;
+                }
+            }
+            self.optimize(environment);
+        }
+        self.step += 1;
+
+        let (steer, throttle) = self.plan[0];
+        Action { steer, throttle }
+    }
+
+    fn reset(&mut self) {
+        self.plan = vec![(0.0, 1.0); self.config.horizon];
+        self.step = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn in_range(action: &Action) -> bool {
+        (-1.0..=1.0).contains(&action.steer) && (-1.0..=1.0).contains(&action.throttle)
+    }
+
+    #[test]
+    fn first_action_is_in_range() {
+        let environment = Environment::new(Some(4));
+        let mut controller = MpcController::new(MpcConfig::default());
+        assert!(in_range(&controller.control(&environment)));
+    }
+
+    #[test]
+    fn degenerate_configs_are_clamped() {
+        let mut environment = Environment::new(Some(9));
+        let mut controller = MpcController::new(MpcConfig {
+            horizon: 0,
+            action_repeat: 0,
+            samples: 0,
+            ..MpcConfig::default()
+        });
+        for _ in 0..5 {
+            let action = controller.control(&environment);
+            assert!(in_range(&action));
+            environment.step(&action, true);
+        }
+        controller.reset();
+        assert!(in_range(&controller.control(&environment)));
+    }
+
+    #[test]
+    fn seeded_plans_are_reproducible() {
+        let environment = Environment::new(Some(2));
+        let config = MpcConfig {
+            samples: 16,
+            seed: 3,
+            ..MpcConfig::default()
+        };
+        let first = MpcController::new(config.clone()).control(&environment);
+        let second = MpcController::new(config).control(&environment);
+        assert_eq!(
+            (first.steer, first.throttle),
+            (second.steer, second.throttle)
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use crate::ActionSelector;
+use racer_logic::environment::Action;
+
+/// (steer, throttle) of each output of a discrete policy without an action table in its
+/// metadata.
+pub const DISCRETE_ACTIONS: [(f32, f32); 9] = [
+    (1.0, 1.0),
+    (0.0, 1.0),
+    (-1.0, 1.0),
+    (1.0, 0.0),
+    (0.0, 0.0),
+    (-1.0, 0.0),
+    (1.0, -1.0),
+    (0.0, -1.0),
+    (-1.0, -1.0),
+];
+
+/// How the model output is turned into an [`Action`]. Discrete actions are picked by an
+/// [`ActionSelector`], continuous ones are used as they are.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActionMapping {
+    /// One score per (steer, throttle) entry of the table.
+    Discrete(Vec<(f32, f32)>),
+    /// Steer and throttle predicted directly.
+    Continuous,
+}
+
+impl ActionMapping {
+    /// Number of model outputs the mapping expects.
+    pub fn output_size(&self) -> usize {
+        match self {
+            ActionMapping::Discrete(table) => table.len(),
+            ActionMapping::Continuous => 2,
+        }
+    }
+
+    /// Mapping of legacy models, guessed from the number of outputs.
+    pub fn from_output_size(size: usize) -> Option<Self> {
+        match size {
+            2 => Some(ActionMapping::Continuous),
+            size if size == DISCRETE_ACTIONS.len() => {
+                Some(ActionMapping::Discrete(DISCRETE_ACTIONS.to_vec()))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn action(&self, output: &[f32], selector: &ActionSelector) -> Action {
+        self.choose(output, selector).0
+    }
+
+    /// The action for `output` with the index of the selected discrete action.
+    pub fn choose(&self, output: &[f32], selector: &ActionSelector) -> (Action, Option<usize>) {
+        let (steer, throttle, index) = match self {
+            ActionMapping::Continuous => (output[0], output[1], None),
+            ActionMapping::Discrete(table) => {
+                let index = selector.select(output);
+                let (steer, throttle) = table[index];
+                (steer, throttle, Some(index))
+            }
+        };
+        (Action { steer, throttle }, index)
+    }
+
+    /// "steer, throttle" label of each discrete action, empty for continuous actions.
+    pub fn labels(&self) -> Vec<String> {
+        match self {
+            ActionMapping::Continuous => vec![],
+            ActionMapping::Discrete(table) => table
+                .iter()
+                .map(|(steer, throttle)| format!("{steer:+.1}, {throttle:+.1}"))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! Inference runtimes the policies can be evaluated with, chosen at build time.
+//!
+//! The `ort` feature (default) uses the native ONNX Runtime, the `tract` feature a pure-Rust
+//! evaluator which needs no native libraries. `ort` wins when both are enabled.
+
+use std::fmt;
+
+#[cfg(feature = "ort")]
+mod ort;
+#[cfg(all(feature = "tract", not(feature = "ort")))]
+mod tract;
+
+#[cfg(feature = "ort")]
+pub(crate) use self::ort::Session;
+#[cfg(all(feature = "tract", not(feature = "ort")))]
+pub(crate) use self::tract::Session;
+
+#[cfg(not(any(feature = "ort", feature = "tract")))]
+compile_error!("racer_onnx_controller needs the `ort` or the `tract` feature");
+
+/// Name, element type and shape of a model input or output. Dynamic dimensions are `-1`.
+#[derive(Debug, Clone)]
+pub(crate) struct TensorInfo {
+    pub name: String,
+    pub element_type: String,
+    pub is_f32: bool,
+    pub shape: Vec<i64>,
+}
+
+impl fmt::Display for TensorInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:?}", self.element_type, self.shape)
+    }
+}
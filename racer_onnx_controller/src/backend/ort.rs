@@ -0,0 +1,90 @@
+use super::TensorInfo;
+use crate::Error;
+use ort::{
+    tensor::TensorElementType,
+    value::{Tensor, ValueType},
+};
+
+pub(crate) struct Session {
+    session: ort::session::Session,
+    inputs: Vec<TensorInfo>,
+    outputs: Vec<TensorInfo>,
+}
+
+fn tensor_info(name: &str, value_type: &ValueType) -> TensorInfo {
+    match value_type {
+        ValueType::Tensor { ty, shape, .. } => TensorInfo {
+            name: name.to_owned(),
+            element_type: ty.to_string(),
+            is_f32: *ty == TensorElementType::Float32,
+            shape: shape.to_vec(),
+        },
+        other => TensorInfo {
+            name: name.to_owned(),
+            element_type: other.to_string(),
+            is_f32: false,
+            shape: vec![],
+        },
+    }
+}
+
+impl Session {
+    pub fn load(path: &str, intra_threads: Option<usize>) -> Result<Self, Error> {
+        let mut builder = ort::session::Session::builder()?;
+        if let Some(threads) = intra_threads {
+            builder = builder.with_intra_threads(threads)?;
+        }
+        let session = builder.commit_from_file(path)?;
+        let inputs = session
+            .inputs
+            .iter()
+            .map(|input| tensor_info(&input.name, &input.input_type))
+            .collect();
+        let outputs = session
+            .outputs
+            .iter()
+            .map(|output| tensor_info(&output.name, &output.output_type))
+            .collect();
+        Ok(Self {
+            session,
+            inputs,
+            outputs,
+        })
+    }
+
+    pub fn inputs(&self) -> &[TensorInfo] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[TensorInfo] {
+        &self.outputs
+    }
+
+    pub fn custom_metadata(&self, key: &str) -> Result<Option<String>, Error> {
+        Ok(self.session.metadata()?.custom(key)?)
+    }
+
+    /// Runs the model on `[batch, size]` inputs and returns the requested outputs flattened.
+    pub fn run(
+        &mut self,
+        batch: usize,
+        inputs: Vec<(String, Vec<f32>)>,
+        outputs: &[&str],
+    ) -> Result<Vec<Vec<f32>>, Error> {
+        let inputs = inputs
+            .into_iter()
+            .map(|(name, values)| {
+                let size = values.len() / batch.max(1);
+                Ok((name, Tensor::from_array(([batch, size], values))?))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let session_output = self.session.run(inputs)?;
+        outputs
+            .iter()
+            .map(|&name| {
+                let (_, values) = session_output[name].try_extract_tensor::<f32>()?;
+                Ok(values.to_vec())
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,125 @@
+use super::TensorInfo;
+use crate::Error;
+use tract_onnx::{prelude::*, tract_hir::infer::Factoid};
+
+type Plan = SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>;
+
+pub(crate) struct Session {
+    plan: Plan,
+    inputs: Vec<TensorInfo>,
+    outputs: Vec<TensorInfo>,
+    metadata: Vec<(String, String)>,
+}
+
+fn tensor_info(name: &str, fact: &InferenceFact) -> TensorInfo {
+    let datum_type = fact.datum_type.concretize();
+    TensorInfo {
+        name: name.to_owned(),
+        element_type: datum_type.map_or_else(|| "unknown".to_owned(), |ty| format!("{ty:?}")),
+        is_f32: datum_type == Some(f32::datum_type()),
+        shape: fact
+            .shape
+            .dims()
+            .map(|dim| dim.concretize().and_then(|d| d.to_i64().ok()).unwrap_or(-1))
+            .collect(),
+    }
+}
+
+impl Session {
+    /// Loads the model. tract evaluates it on the calling thread, so `intra_threads` is ignored.
+    pub fn load(path: &str, _intra_threads: Option<usize>) -> Result<Self, Error> {
+        let onnx = tract_onnx::onnx();
+        let proto = onnx.proto_model_for_path(path)?;
+        let model = onnx.model_for_proto_model(&proto)?;
+        let graph = proto.graph.as_ref();
+
+        // tract names outlets after the nodes producing them, the graph keeps the tensor names
+        let names = |values: Option<Vec<String>>, count: usize| {
+            values.unwrap_or_else(|| (0..count).map(|i| i.to_string()).collect())
+        };
+        let input_names = names(
+            graph.map(|g| g.input.iter().map(|i| i.name.clone()).collect()),
+            model.inputs.len(),
+        );
+        let output_names = names(
+            graph.map(|g| g.output.iter().map(|o| o.name.clone()).collect()),
+            model.outputs.len(),
+        );
+        let inputs = model
+            .input_outlets()?
+            .iter()
+            .zip(&input_names)
+            .map(|(outlet, name)| Ok(tensor_info(name, model.outlet_fact(*outlet)?)))
+            .collect::<TractResult<_>>()?;
+        let outputs = model
+            .output_outlets()?
+            .iter()
+            .zip(&output_names)
+            .map(|(outlet, name)| Ok(tensor_info(name, model.outlet_fact(*outlet)?)))
+            .collect::<TractResult<_>>()?;
+
+        Ok(Self {
+            plan: model.into_optimized()?.into_runnable()?,
+            inputs,
+            outputs,
+            metadata: proto
+                .metadata_props
+                .iter()
+                .map(|entry| (entry.key.clone(), entry.value.clone()))
+                .collect(),
+        })
+    }
+
+    pub fn inputs(&self) -> &[TensorInfo] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[TensorInfo] {
+        &self.outputs
+    }
+
+    pub fn custom_metadata(&self, key: &str) -> Result<Option<String>, Error> {
+        Ok(self
+            .metadata
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.clone()))
+    }
+
+    /// Runs the model on `[batch, size]` inputs and returns the requested outputs flattened.
+    pub fn run(
+        &mut self,
+        batch: usize,
+        mut inputs: Vec<(String, Vec<f32>)>,
+        outputs: &[&str],
+    ) -> Result<Vec<Vec<f32>>, Error> {
+        let values = self
+            .inputs
+            .iter()
+            .map(|info| {
+                let index = inputs
+                    .iter()
+                    .position(|(name, _)| *name == info.name)
+                    .ok_or_else(|| Error::MissingTensor(info.name.clone()))?;
+                let (_, values) = inputs.swap_remove(index);
+                let size = values.len() / batch.max(1);
+                let tensor = tract_ndarray::Array2::from_shape_vec((batch, size), values)
+                    .map_err(TractError::from)?;
+                Ok(Tensor::from(tensor).into())
+            })
+            .collect::<Result<TVec<TValue>, Error>>()?;
+
+        let results = self.plan.run(values)?;
+        outputs
+            .iter()
+            .map(|&name| {
+                let index = self
+                    .outputs
+                    .iter()
+                    .position(|info| info.name == name)
+                    .ok_or_else(|| Error::MissingTensor(name.to_owned()))?;
+                Ok(results[index].as_slice::<f32>()?.to_vec())
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{ActionSelector, Error, OnnxController, PolicyState};
+use racer_logic::{
+    environment::{Environment, Observation},
+    metrics::{EpisodeMetrics, Termination},
+};
+
+/// Rewards collected in one environment by the [`BatchEvaluator`].
+#[derive(Debug, Clone)]
+pub struct Episode {
+    pub rewards: Vec<f32>,
+    /// Metrics numbered by the position of the environment in the batch.
+    pub metrics: EpisodeMetrics,
+}
+
+/// Evaluates a policy in many environments at once.
+///
+/// The environments are stepped in lockstep so that every tick needs just one inference over
+/// the observations of all environments still running.
+pub struct BatchEvaluator {
+    controller: OnnxController,
+    max_steps: usize,
+}
+
+impl BatchEvaluator {
+    pub fn new(controller: OnnxController, max_steps: usize) -> Self {
+        Self {
+            controller,
+            max_steps,
+        }
+    }
+
+    pub fn controller(&self) -> &OnnxController {
+        &self.controller
+    }
+
+    /// Runs an episode in each environment until it finishes or `max_steps` steps pass.
+    ///
+    /// Every environment selects its actions with its own random generator seeded from
+    /// [`Environment::seed`], so an episode does not depend on the rest of the batch.
+    pub fn evaluate(&mut self, mut environments: Vec<Environment>) -> Result<Vec<Episode>, Error> {
+        let mut episodes: Vec<Episode> = environments
+            .iter()
+            .enumerate()
+            .map(|(i, environment)| Episode {
+                rewards: vec![],
+                metrics: EpisodeMetrics::new(i as u64, environment.seed),
+            })
+            .collect();
+        let selectors: Vec<ActionSelector> = environments
+            .iter()
+            .map(|environment| {
+                ActionSelector::new(
+                    self.controller.selector.selection(),
+                    self.controller.metadata.output_kind,
+                    environment.seed,
+                )
+            })
+            .collect();
+        let mut states = vec![PolicyState::default(); environments.len()];
+        let mut running: Vec<usize> = (0..environments.len()).collect();
+
+        for _ in 0..self.max_steps {
+            if running.is_empty() {
+                break;
+            }
+            let observations: Vec<&Observation> = running
+                .iter()
+                .map(|&i| &environments[i].observation)
+                .collect();
+            let mut running_states: Vec<PolicyState> = running
+                .iter()
+                .map(|&i| std::mem::take(&mut states[i]))
+                .collect();
+            let running_selectors: Vec<&ActionSelector> =
+                running.iter().map(|&i| &selectors[i]).collect();
+            let actions =
+                self.controller
+                    .actions(&observations, &mut running_states, &running_selectors)?;
+            for (&i, state) in running.iter().zip(running_states) {
+                states[i] = state;
+            }
+
+            for (&i, action) in running.iter().zip(&actions) {
+                let outcome = environments[i].step(action, true);
+                episodes[i].rewards.push(outcome.reward);
+                episodes[i].metrics.step(&environments[i], &outcome);
+            }
+            running.retain(|&i| !episodes[i].metrics.finished);
+        }
+        for (episode, environment) in episodes.iter_mut().zip(&environments) {
+            episode.metrics.end(environment, Termination::StepLimit);
+        }
+        Ok(episodes)
+    }
+}
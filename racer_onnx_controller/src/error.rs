@@ -0,0 +1,112 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    #[cfg(feature = "ort")]
+    Ort(ort::Error),
+    #[cfg(feature = "tract")]
+    Tract(tract_onnx::prelude::TractError),
+    /// The model has no tensor with the expected name.
+    MissingTensor(String),
+    /// A tensor of the model is not `f32` with a `[batch, size]` shape.
+    TensorShape { name: String, found: String },
+    /// The model input does not match the length of the observation vector.
+    InputSize { expected: usize, found: usize },
+    /// The model output does not match the action mapping.
+    OutputSize {
+        expected: Option<usize>,
+        found: usize,
+    },
+    /// A custom metadata entry of the model could not be parsed.
+    Metadata { key: String, message: String },
+    /// The model was trained with a different version of the observation vector.
+    ObservationVersion { expected: u32, found: u32 },
+    /// The model was trained with different observation features.
+    ObservationFeatures {
+        expected: Vec<String>,
+        found: Vec<String>,
+    },
+    /// The input and output tensors of a recurrent state differ in size.
+    RecurrentSize { input: String, output: String },
+    /// An action selection mode could not be parsed.
+    Selection(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "ort")]
+            Error::Ort(e) => write!(f, "ONNX runtime error: {e}"),
+            #[cfg(feature = "tract")]
+            Error::Tract(e) => write!(f, "tract error: {e}"),
+            Error::MissingTensor(name) => write!(f, "the model has no tensor named '{name}'"),
+            Error::TensorShape { name, found } => write!(
+                f,
+                "tensor '{name}' should be f32 with shape [batch, size], found {found}"
+            ),
+            Error::InputSize { expected, found } => write!(
+                f,
+                "the model expects {found} observation features, the environment provides {expected}"
+            ),
+            Error::OutputSize {
+                expected: Some(expected),
+                found,
+            } => write!(
+                f,
+                "the model has {found} outputs, its action mapping expects {expected}"
+            ),
+            Error::OutputSize {
+                expected: None,
+                found,
+            } => write!(
+                f,
+                "the model has {found} outputs, expected {} discrete actions or 2 continuous ones",
+                crate::DISCRETE_ACTIONS.len()
+            ),
+            Error::Metadata { key, message } => {
+                write!(f, "invalid model metadata '{key}': {message}")
+            }
+            Error::ObservationVersion { expected, found } => write!(
+                f,
+                "the model was trained with observation version {found}, the environment provides version {expected}"
+            ),
+            Error::ObservationFeatures { expected, found } => write!(
+                f,
+                "the model was trained with observation features [{}], the environment provides [{}]",
+                found.join(", "),
+                expected.join(", ")
+            ),
+            Error::RecurrentSize { input, output } => write!(
+                f,
+                "recurrent state tensors '{input}' and '{output}' have different sizes"
+            ),
+            Error::Selection(message) => write!(f, "invalid action selection {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "ort")]
+            Error::Ort(e) => Some(e),
+            #[cfg(feature = "tract")]
+            Error::Tract(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "ort")]
+impl From<ort::Error> for Error {
+    fn from(e: ort::Error) -> Self {
+        Error::Ort(e)
+    }
+}
+
+#[cfg(feature = "tract")]
+impl From<tract_onnx::prelude::TractError> for Error {
+    fn from(e: tract_onnx::prelude::TractError) -> Self {
+        Error::Tract(e)
+    }
+}
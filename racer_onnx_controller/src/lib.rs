@@ -1,59 +1,293 @@
-use std::collections::HashMap;
-
+use backend::{Session, TensorInfo};
 use racer_logic::{
-    controller::Controller,
-    environment::{Action, Observation},
+    controller::{Controller, Diagnostics},
+    environment::{Action, Environment, Observation},
 };
 
+mod action;
+mod backend;
+mod batch;
+mod error;
+mod metadata;
+mod selection;
+mod state;
+pub use action::{ActionMapping, DISCRETE_ACTIONS};
+pub use batch::{BatchEvaluator, Episode};
+pub use error::Error;
+pub use metadata::{Normalization, PolicyKind, PolicyMetadata, RecurrentState};
+pub use selection::{ActionSelector, OutputKind, Selection};
+pub use state::PolicyState;
+
 pub struct OnnxController {
-    session: ort::session::Session,
-    output_to_action: HashMap<usize, (f32, f32)>,
+    session: Session,
+    metadata: PolicyMetadata,
+    action_mapping: ActionMapping,
+    selector: ActionSelector,
+    /// Whether the model accepts more than one observation per inference.
+    dynamic_batch: bool,
+    /// Sizes of the tensors in [`PolicyMetadata::recurrent_state`].
+    recurrent_sizes: Vec<usize>,
+    /// State of the episode driven through [`Controller::control`].
+    state: PolicyState,
+    /// Decision of the last [`Controller::control`] call.
+    diagnostics: Option<Diagnostics>,
 }
 
 impl OnnxController {
-    pub fn new(path: &str) -> Self {
-        OnnxController {
-            session: ort::session::Session::builder()
-                .unwrap()
-                .commit_from_file(path)
-                .unwrap(),
-            output_to_action: HashMap::from([
-                (0, (1.0, 1.0)),
-                (1, (0.0, 1.0)),
-                (2, (-1.0, 1.0)),
-                (3, (1.0, 0.0)),
-                (4, (0.0, 0.0)),
-                (5, (-1.0, 0.0)),
-                (6, (1.0, -1.0)),
-                (7, (0.0, -1.0)),
-                (8, (-1.0, -1.0)),
-            ]),
+    /// Loads the model and checks it against the observation and action contract in its
+    /// metadata, see [`PolicyMetadata`]. Discrete actions are selected the way the policy was
+    /// trained, see [`Selection::for_policy`].
+    pub fn new(path: &str) -> Result<Self, Error> {
+        Self::load(path, None)
+    }
+
+    /// Like [`OnnxController::new`], limiting the number of threads used to parallelize a
+    /// single inference. The runtime picks the number of threads when `intra_threads` is `None`.
+    pub fn load(path: &str, intra_threads: Option<usize>) -> Result<Self, Error> {
+        let session = Session::load(path, intra_threads)?;
+        let metadata = PolicyMetadata::read(|key| session.custom_metadata(key))?;
+        metadata.check_observation()?;
+
+        let input = session
+            .inputs()
+            .iter()
+            .find(|input| input.name == metadata.input_name)
+            .ok_or_else(|| Error::MissingTensor(metadata.input_name.clone()))?;
+        let input_size = tensor_size(input)?;
+        let dynamic_batch = input.shape[0] == -1;
+        let observation_size = Observation::feature_names().len() * metadata.frame_stack;
+        if input_size != observation_size {
+            return Err(Error::InputSize {
+                expected: observation_size,
+                found: input_size,
+            });
+        }
+
+        let output = session
+            .outputs()
+            .iter()
+            .find(|output| output.name == metadata.output_name)
+            .ok_or_else(|| Error::MissingTensor(metadata.output_name.clone()))?;
+        let output_size = tensor_size(output)?;
+        let action_mapping = match &metadata.action_mapping {
+            Some(mapping) if mapping.output_size() == output_size => mapping.clone(),
+            Some(mapping) => {
+                return Err(Error::OutputSize {
+                    expected: Some(mapping.output_size()),
+                    found: output_size,
+                });
+            }
+            None => ActionMapping::from_output_size(output_size).ok_or(Error::OutputSize {
+                expected: None,
+                found: output_size,
+            })?,
+        };
+
+        let mut recurrent_sizes = vec![];
+        for recurrent in &metadata.recurrent_state {
+            let input = session
+                .inputs()
+                .iter()
+                .find(|input| input.name == recurrent.input)
+                .ok_or_else(|| Error::MissingTensor(recurrent.input.clone()))?;
+            let output = session
+                .outputs()
+                .iter()
+                .find(|output| output.name == recurrent.output)
+                .ok_or_else(|| Error::MissingTensor(recurrent.output.clone()))?;
+            let size = tensor_size(input)?;
+            let output_size = tensor_size(output)?;
+            if size != output_size {
+                return Err(Error::RecurrentSize {
+                    input: input.name.clone(),
+                    output: output.name.clone(),
+                });
+            }
+            recurrent_sizes.push(size);
+        }
+
+        let selector = ActionSelector::new(
+            Selection::for_policy(metadata.policy),
+            metadata.output_kind,
+            0,
+        );
+        Ok(OnnxController {
+            session,
+            metadata,
+            action_mapping,
+            selector,
+            dynamic_batch,
+            recurrent_sizes,
+            state: PolicyState::default(),
+            diagnostics: None,
+        })
+    }
+
+    /// Overrides how discrete actions are selected, `seed` seeds the random generator of the
+    /// stochastic modes in [`Controller::control`]. The [`BatchEvaluator`] seeds one generator
+    /// per environment instead.
+    pub fn with_selection(mut self, selection: Selection, seed: u64) -> Self {
+        self.selector = ActionSelector::new(selection, self.metadata.output_kind, seed);
+        self
+    }
+
+    pub fn metadata(&self) -> &PolicyMetadata {
+        &self.metadata
+    }
+
+    pub fn action_mapping(&self) -> &ActionMapping {
+        &self.action_mapping
+    }
+
+    /// Features of `observation` normalized the way the model was trained.
+    fn normalized(&self, observation: &Observation) -> Vec<f32> {
+        let mut features: Vec<f32> = observation.clone().into();
+        if let Some(normalization) = &self.metadata.normalization {
+            normalization.apply(&mut features);
         }
+        features
+    }
+
+    fn infer(&mut self, observation: &Observation) -> Result<Vec<f32>, Error> {
+        let mut state = std::mem::take(&mut self.state);
+        let output = self.infer_batch(&[observation], std::slice::from_mut(&mut state));
+        self.state = state;
+        Ok(output?.remove(0))
+    }
+
+    /// Runs a single `[N, input size]` inference and returns the output row of each
+    /// observation. `states` holds the episode state of each observation and is updated with
+    /// it. Models with a fixed batch size of one are run once per observation.
+    pub fn infer_batch(
+        &mut self,
+        observations: &[&Observation],
+        states: &mut [PolicyState],
+    ) -> Result<Vec<Vec<f32>>, Error> {
+        assert_eq!(observations.len(), states.len());
+        if !self.dynamic_batch && observations.len() > 1 {
+            return observations
+                .iter()
+                .zip(states.chunks_mut(1))
+                .map(|(observation, state)| self.infer_batch(&[observation], state))
+                .map(|rows| rows.map(|mut rows| rows.remove(0)))
+                .collect();
+        }
+
+        let batch = observations.len();
+        let mut input = vec![];
+        for (&observation, state) in observations.iter().zip(states.iter_mut()) {
+            let obs_vec = self.normalized(observation);
+            input.extend(state.stack(obs_vec, self.metadata.frame_stack));
+        }
+        let mut inputs = vec![(self.metadata.input_name.clone(), input)];
+        for (i, recurrent) in self.metadata.recurrent_state.iter().enumerate() {
+            let values: Vec<f32> = states
+                .iter_mut()
+                .flat_map(|state| state.recurrent(&self.recurrent_sizes)[i].clone())
+                .collect();
+            inputs.push((recurrent.input.clone(), values));
+        }
+
+        let output_names: Vec<&str> = std::iter::once(self.metadata.output_name.as_str())
+            .chain(
+                self.metadata
+                    .recurrent_state
+                    .iter()
+                    .map(|recurrent| recurrent.output.as_str()),
+            )
+            .collect();
+        let mut outputs = self
+            .session
+            .run(batch, inputs, &output_names)?
+            .into_iter()
+            .map(|values| {
+                let size = values.len() / batch.max(1);
+                values
+                    .chunks(size.max(1))
+                    .map(<[f32]>::to_vec)
+                    .collect::<Vec<_>>()
+            });
+        let actions = outputs.next().unwrap_or_default();
+        let mut recurrent: Vec<Vec<Vec<f32>>> = outputs.collect();
+        for (b, state) in states.iter_mut().enumerate() {
+            state.set_recurrent(
+                recurrent
+                    .iter_mut()
+                    .map(|rows| std::mem::take(&mut rows[b]))
+                    .collect(),
+            );
+        }
+        Ok(actions)
+    }
+
+    /// Actions for a batch of observations, see [`OnnxController::infer_batch`], each
+    /// discrete one picked by the selector at the same position in `selectors`.
+    pub fn actions(
+        &mut self,
+        observations: &[&Observation],
+        states: &mut [PolicyState],
+        selectors: &[&ActionSelector],
+    ) -> Result<Vec<Action>, Error> {
+        Ok(self
+            .infer_batch(observations, states)?
+            .iter()
+            .zip(selectors)
+            .map(|(output, selector)| self.action_mapping.action(output, selector))
+            .collect())
+    }
+}
+
+/// Size of a `[batch, size]` f32 tensor, the batch dimension may be dynamic.
+fn tensor_size(tensor: &TensorInfo) -> Result<usize, Error> {
+    match tensor.shape[..] {
+        [batch, size] if tensor.is_f32 && (batch == -1 || batch == 1) && size > 0 => {
+            Ok(size as usize)
+        }
+        _ => Err(Error::TensorShape {
+            name: tensor.name.clone(),
+            found: tensor.to_string(),
+        }),
     }
 }
 
 impl Controller for OnnxController {
-    fn control(&mut self, o: &Observation) -> Action {
-        let obs_vec: Vec<f32> = o.clone().into();
-        let input_tensor = ort::value::Tensor::from_array(([1, obs_vec.len()], obs_vec)).unwrap();
-        let input = ort::inputs!["input" => input_tensor];
-        let session_output = self.session.run(input).unwrap();
-        let output = session_output["output"].try_extract_array::<f32>().unwrap();
-
-        let mut max_index = 0;
-        let mut max_val = 0.0;
-        for (index, &val) in output.into_iter().enumerate() {
-            if val > max_val {
-                max_val = val;
-                max_index = index;
+    fn control(&mut self, environment: &Environment) -> Action {
+        match self.infer(&environment.observation) {
+            Ok(output) => {
+                let (action, chosen) = self.action_mapping.choose(&output, &self.selector);
+                let probabilities = match self.action_mapping {
+                    ActionMapping::Discrete(_) => self.metadata.output_kind.probabilities(&output),
+                    ActionMapping::Continuous => vec![],
+                };
+                self.diagnostics = Some(Diagnostics {
+                    probabilities: self
+                        .action_mapping
+                        .labels()
+                        .into_iter()
+                        .zip(probabilities)
+                        .collect(),
+                    chosen,
+                    observation: self.normalized(&environment.observation),
+                });
+                action
+            }
+            Err(e) => {
+                eprintln!("Inference failed: {e}");
+                self.diagnostics = None;
+                Action {
+                    steer: 0.0,
+                    throttle: 0.0,
+                }
             }
         }
+    }
 
-        let action = self.output_to_action[&max_index];
+    fn reset(&mut self) {
+        self.state = PolicyState::default();
+        self.diagnostics = None;
+    }
 
-        Action {
-            steer: action.0,
-            throttle: action.1,
-        }
+    fn diagnostics(&self) -> Option<&Diagnostics> {
+        self.diagnostics.as_ref()
     }
 }
@@ -0,0 +1,363 @@
+use crate::{ActionMapping, Error, OutputKind};
+use racer_logic::environment::{OBSERVATION_VERSION, Observation};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+const INPUT_NAME_KEY: &str = "racer.input_name";
+const OUTPUT_NAME_KEY: &str = "racer.output_name";
+const OBSERVATION_VERSION_KEY: &str = "racer.observation_version";
+const OBSERVATION_FEATURES_KEY: &str = "racer.observation_features";
+const ACTION_MAPPING_KEY: &str = "racer.action_mapping";
+const NORMALIZATION_KEY: &str = "racer.normalization";
+const POLICY_KEY: &str = "racer.policy";
+const OUTPUT_KIND_KEY: &str = "racer.output_kind";
+const FRAME_STACK_KEY: &str = "racer.frame_stack";
+const RECURRENT_STATE_KEY: &str = "racer.recurrent_state";
+
+/// Affine transformation `x * scale + bias` applied to the observation before inference.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Normalization {
+    pub scale: Vec<f32>,
+    pub bias: Vec<f32>,
+}
+
+impl Normalization {
+    pub fn apply(&self, observation: &mut [f32]) {
+        for ((x, scale), bias) in observation.iter_mut().zip(&self.scale).zip(&self.bias) {
+            *x = *x * scale + bias;
+        }
+    }
+}
+
+/// Pair of tensors carrying recurrent state, e.g. the hidden state of an LSTM, from one step to
+/// the next. Both are `[batch, size]` f32 tensors, the `output` of one step is fed to the `input`
+/// of the next one and zeros are fed at the start of an episode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecurrentState {
+    pub input: String,
+    pub output: String,
+}
+
+/// Whether the policy was trained to be sampled from or to be followed greedily.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyKind {
+    #[default]
+    Greedy,
+    Stochastic,
+}
+
+/// Description of a policy stored in the custom metadata of the ONNX model when it is exported.
+///
+/// All keys are optional so that models exported before the metadata existed still load, with
+/// the `input`/`output` tensor names and an action mapping guessed from the output size.
+///
+/// | key | value |
+/// |-----|-------|
+/// | `racer.input_name` | name of the observation input tensor |
+/// | `racer.output_name` | name of the action output tensor |
+/// | `racer.observation_version` | [`OBSERVATION_VERSION`] the model was trained with |
+/// | `racer.observation_features` | JSON list of the observation feature names |
+/// | `racer.action_mapping` | `"continuous"` or JSON list of `[steer, throttle]` pairs |
+/// | `racer.normalization` | JSON object with `scale` and `bias` lists |
+/// | `racer.policy` | `"greedy"` or `"stochastic"` |
+/// | `racer.output_kind` | `"probabilities"` or `"logits"` |
+/// | `racer.frame_stack` | number of consecutive observations in the input, oldest first |
+/// | `racer.recurrent_state` | JSON list of [`RecurrentState`] objects |
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyMetadata {
+    pub input_name: String,
+    pub output_name: String,
+    pub observation_version: Option<u32>,
+    pub observation_features: Option<Vec<String>>,
+    pub action_mapping: Option<ActionMapping>,
+    pub normalization: Option<Normalization>,
+    pub policy: PolicyKind,
+    pub output_kind: OutputKind,
+    pub frame_stack: usize,
+    pub recurrent_state: Vec<RecurrentState>,
+}
+
+impl PolicyMetadata {
+    /// Reads the metadata through `custom`, which looks up a custom metadata key of the model.
+    pub fn read(custom: impl Fn(&str) -> Result<Option<String>, Error>) -> Result<Self, Error> {
+        let action_mapping = match custom(ACTION_MAPPING_KEY)? {
+            Some(value) if value.trim() == "continuous" => Some(ActionMapping::Continuous),
+            Some(value) => Some(ActionMapping::Discrete(parse(ACTION_MAPPING_KEY, &value)?)),
+            None => None,
+        };
+
+        Ok(Self {
+            input_name: custom(INPUT_NAME_KEY)?.unwrap_or_else(|| "input".to_owned()),
+            output_name: custom(OUTPUT_NAME_KEY)?.unwrap_or_else(|| "output".to_owned()),
+            observation_version: parse_optional(&custom, OBSERVATION_VERSION_KEY)?,
+            observation_features: parse_optional(&custom, OBSERVATION_FEATURES_KEY)?,
+            action_mapping,
+            normalization: parse_optional(&custom, NORMALIZATION_KEY)?,
+            policy: parse_name(&custom, POLICY_KEY)?.unwrap_or_default(),
+            output_kind: parse_name(&custom, OUTPUT_KIND_KEY)?.unwrap_or_default(),
+            frame_stack: parse_optional(&custom, FRAME_STACK_KEY)?.unwrap_or(1),
+            recurrent_state: parse_optional(&custom, RECURRENT_STATE_KEY)?.unwrap_or_default(),
+        })
+    }
+
+    /// Key-value pairs to store in the custom metadata of an exported model, the inverse of
+    /// [`PolicyMetadata::read`].
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let mut entries = vec![
+            (INPUT_NAME_KEY, self.input_name.clone()),
+            (OUTPUT_NAME_KEY, self.output_name.clone()),
+            (POLICY_KEY, name(&self.policy)),
+            (OUTPUT_KIND_KEY, name(&self.output_kind)),
+            (FRAME_STACK_KEY, self.frame_stack.to_string()),
+        ];
+        if let Some(version) = self.observation_version {
+            entries.push((OBSERVATION_VERSION_KEY, version.to_string()));
+        }
+        if let Some(features) = &self.observation_features {
+            entries.push((OBSERVATION_FEATURES_KEY, to_json(features)));
+        }
+        match &self.action_mapping {
+            Some(ActionMapping::Continuous) => {
+                entries.push((ACTION_MAPPING_KEY, "continuous".to_owned()))
+            }
+            Some(ActionMapping::Discrete(table)) => {
+                entries.push((ACTION_MAPPING_KEY, to_json(table)))
+            }
+            None => {}
+        }
+        if let Some(normalization) = &self.normalization {
+            entries.push((NORMALIZATION_KEY, to_json(normalization)));
+        }
+        if !self.recurrent_state.is_empty() {
+            entries.push((RECURRENT_STATE_KEY, to_json(&self.recurrent_state)));
+        }
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value))
+            .collect()
+    }
+
+    /// Checks that the model was trained on the observations the environment produces.
+    pub fn check_observation(&self) -> Result<(), Error> {
+        if let Some(version) = self.observation_version
+            && version != OBSERVATION_VERSION
+        {
+            return Err(Error::ObservationVersion {
+                expected: OBSERVATION_VERSION,
+                found: version,
+            });
+        }
+
+        if self.frame_stack == 0 {
+            return Err(Error::Metadata {
+                key: FRAME_STACK_KEY.to_owned(),
+                message: "at least one observation has to be stacked".to_owned(),
+            });
+        }
+
+        let expected = Observation::feature_names();
+        if let Some(features) = &self.observation_features
+            && *features != expected
+        {
+            return Err(Error::ObservationFeatures {
+                expected,
+                found: features.clone(),
+            });
+        }
+
+        if let Some(normalization) = &self.normalization
+            && (normalization.scale.len() != expected.len()
+                || normalization.bias.len() != expected.len())
+        {
+            return Err(Error::Metadata {
+                key: NORMALIZATION_KEY.to_owned(),
+                message: format!("expected {} scale and bias values", expected.len()),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn parse<T: DeserializeOwned>(key: &str, value: &str) -> Result<T, Error> {
+    serde_json::from_str(value).map_err(|e| Error::Metadata {
+        key: key.to_owned(),
+        message: e.to_string(),
+    })
+}
+
+fn parse_optional<T: DeserializeOwned>(
+    custom: &impl Fn(&str) -> Result<Option<String>, Error>,
+    key: &str,
+) -> Result<Option<T>, Error> {
+    custom(key)?.map(|value| parse(key, &value)).transpose()
+}
+
+fn to_json<T: Serialize + ?Sized>(value: &T) -> String {
+    serde_json::to_string(value).expect("metadata values serialize to JSON")
+}
+
+/// Plain string value of a unit enum variant, the inverse of [`parse_name`].
+fn name<T: Serialize>(value: &T) -> String {
+    to_json(value).trim_matches('"').to_owned()
+}
+
+/// Parses a plain string value, such as `greedy`, into a unit enum variant.
+fn parse_name<T: DeserializeOwned>(
+    custom: &impl Fn(&str) -> Result<Option<String>, Error>,
+    key: &str,
+) -> Result<Option<T>, Error> {
+    custom(key)?
+        .map(|value| parse(key, &format!("\"{}\"", value.trim())))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DISCRETE_ACTIONS;
+    use std::collections::HashMap;
+
+    /// Reads metadata from `entries` as if they were the custom metadata of a model.
+    fn read(entries: &[(String, String)]) -> Result<PolicyMetadata, Error> {
+        let map: HashMap<&str, &str> = entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        PolicyMetadata::read(|key| Ok(map.get(key).map(|value| value.to_string())))
+    }
+
+    fn entry(key: &str, value: &str) -> (String, String) {
+        (key.to_owned(), value.to_owned())
+    }
+
+    fn metadata() -> PolicyMetadata {
+        let features = Observation::feature_names();
+        PolicyMetadata {
+            input_name: "obs".to_owned(),
+            output_name: "probs".to_owned(),
+            observation_version: Some(OBSERVATION_VERSION),
+            observation_features: Some(features.clone()),
+            action_mapping: Some(ActionMapping::Discrete(DISCRETE_ACTIONS.to_vec())),
+            normalization: Some(Normalization {
+                scale: vec![0.5; features.len()],
+                bias: vec![-1.0; features.len()],
+            }),
+            policy: PolicyKind::Stochastic,
+            output_kind: OutputKind::Logits,
+            frame_stack: 4,
+            recurrent_state: vec![RecurrentState {
+                input: "h_in".to_owned(),
+                output: "h_out".to_owned(),
+            }],
+        }
+    }
+
+    #[test]
+    fn entries_round_trip() {
+        let metadata = metadata();
+        assert_eq!(read(&metadata.entries()).unwrap(), metadata);
+
+        let continuous = PolicyMetadata {
+            action_mapping: Some(ActionMapping::Continuous),
+            normalization: None,
+            observation_version: None,
+            observation_features: None,
+            recurrent_state: vec![],
+            ..metadata
+        };
+        assert_eq!(read(&continuous.entries()).unwrap(), continuous);
+    }
+
+    #[test]
+    fn missing_keys_have_defaults() {
+        let metadata = read(&[]).unwrap();
+        assert_eq!(metadata.input_name, "input");
+        assert_eq!(metadata.output_name, "output");
+        assert_eq!(metadata.observation_version, None);
+        assert_eq!(metadata.observation_features, None);
+        assert_eq!(metadata.action_mapping, None);
+        assert_eq!(metadata.normalization, None);
+        assert_eq!(metadata.policy, PolicyKind::Greedy);
+        assert_eq!(metadata.output_kind, OutputKind::default());
+        assert_eq!(metadata.frame_stack, 1);
+        assert!(metadata.recurrent_state.is_empty());
+        assert!(metadata.check_observation().is_ok());
+    }
+
+    #[test]
+    fn action_mappings() {
+        let continuous = read(&[entry(ACTION_MAPPING_KEY, " continuous ")]).unwrap();
+        assert_eq!(continuous.action_mapping, Some(ActionMapping::Continuous));
+
+        let table = read(&[entry(ACTION_MAPPING_KEY, "[[1.0, 0.5], [-1, 0]]")]).unwrap();
+        assert_eq!(
+            table.action_mapping,
+            Some(ActionMapping::Discrete(vec![(1.0, 0.5), (-1.0, 0.0)]))
+        );
+
+        let error = read(&[entry(ACTION_MAPPING_KEY, "sideways")]).unwrap_err();
+        assert!(matches!(error, Error::Metadata { key, .. } if key == ACTION_MAPPING_KEY));
+    }
+
+    #[test]
+    fn invalid_values_name_their_key() {
+        for (key, value) in [
+            (POLICY_KEY, "random"),
+            (OUTPUT_KIND_KEY, "scores"),
+            (FRAME_STACK_KEY, "-1"),
+            (NORMALIZATION_KEY, "{}"),
+        ] {
+            let error = read(&[entry(key, value)]).unwrap_err();
+            assert!(
+                matches!(&error, Error::Metadata { key: k, .. } if k == key),
+                "{key}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn observation_mismatches_are_rejected() {
+        let version = PolicyMetadata {
+            observation_version: Some(OBSERVATION_VERSION + 1),
+            ..metadata()
+        };
+        assert!(matches!(
+            version.check_observation(),
+            Err(Error::ObservationVersion { .. })
+        ));
+
+        let mut features = Observation::feature_names();
+        features.swap(0, 1);
+        let features = PolicyMetadata {
+            observation_features: Some(features),
+            ..metadata()
+        };
+        assert!(matches!(
+            features.check_observation(),
+            Err(Error::ObservationFeatures { .. })
+        ));
+
+        let normalization = PolicyMetadata {
+            normalization: Some(Normalization {
+                scale: vec![1.0],
+                bias: vec![0.0],
+            }),
+            ..metadata()
+        };
+        assert!(matches!(
+            normalization.check_observation(),
+            Err(Error::Metadata { key, .. }) if key == NORMALIZATION_KEY
+        ));
+
+        let frame_stack = PolicyMetadata {
+            frame_stack: 0,
+            ..metadata()
+        };
+        assert!(matches!(
+            frame_stack.check_observation(),
+            Err(Error::Metadata { key, .. }) if key == FRAME_STACK_KEY
+        ));
+
+        assert!(metadata().check_observation().is_ok());
+    }
+}
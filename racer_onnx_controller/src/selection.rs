@@ -0,0 +1,281 @@
+use crate::{Error, PolicyKind};
+use macroquad::rand::RandGenerator;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// What the scores of a discrete policy output are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputKind {
+    /// Softmax probabilities, the output of the models exported by `research/train_reinforce.py`.
+    #[default]
+    Probabilities,
+    /// Unnormalized log-probabilities.
+    Logits,
+}
+
+impl OutputKind {
+    /// Probabilities of the actions scored by `scores`.
+    pub fn probabilities(self, scores: &[f32]) -> Vec<f32> {
+        match self {
+            OutputKind::Probabilities => scores.to_vec(),
+            OutputKind::Logits => {
+                let max = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+                let weights: Vec<f32> = scores.iter().map(|&logit| (logit - max).exp()).collect();
+                let total: f32 = weights.iter().sum();
+                weights.iter().map(|weight| weight / total).collect()
+            }
+        }
+    }
+}
+
+/// How a discrete action is picked from the scores of the model output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Selection {
+    /// Always the highest scoring action.
+    Greedy,
+    /// An action sampled from the policy distribution, sharpened (`temperature < 1`) or
+    /// flattened (`temperature > 1`).
+    Sample { temperature: f32 },
+    /// A uniformly random action with probability `epsilon`, the highest scoring one otherwise.
+    EpsilonGreedy { epsilon: f32 },
+}
+
+impl Selection {
+    /// Selection matching how the policy was trained.
+    pub fn for_policy(policy: PolicyKind) -> Self {
+        match policy {
+            PolicyKind::Greedy => Selection::Greedy,
+            PolicyKind::Stochastic => Selection::Sample { temperature: 1.0 },
+        }
+    }
+}
+
+impl FromStr for Selection {
+    type Err = Error;
+
+    /// Parses `greedy`, `sample`, `sample:<temperature>` or `epsilon:<epsilon>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |message: &str| Error::Selection(format!("'{s}': {message}"));
+        let (mode, value) = match s.split_once(':') {
+            Some((mode, value)) => (
+                mode,
+                Some(value.parse::<f32>().map_err(|e| invalid(&e.to_string()))?),
+            ),
+            None => (s, None),
+        };
+
+        match (mode, value) {
+            ("greedy", None) => Ok(Selection::Greedy),
+            ("sample", None) => Ok(Selection::Sample { temperature: 1.0 }),
+            ("sample", Some(temperature)) if temperature > 0.0 => {
+                Ok(Selection::Sample { temperature })
+            }
+            ("sample", Some(_)) => Err(invalid("the temperature must be positive")),
+            ("epsilon", Some(epsilon)) if (0.0..=1.0).contains(&epsilon) => {
+                Ok(Selection::EpsilonGreedy { epsilon })
+            }
+            ("epsilon", _) => Err(invalid("epsilon must be between 0 and 1")),
+            _ => Err(invalid(
+                "expected greedy, sample, sample:<temperature> or epsilon:<epsilon>",
+            )),
+        }
+    }
+}
+
+/// Picks discrete actions according to a [`Selection`] using its own seeded random generator,
+/// so that episodes with the same seed are reproducible.
+pub struct ActionSelector {
+    selection: Selection,
+    output_kind: OutputKind,
+    rng: RandGenerator,
+}
+
+impl ActionSelector {
+    pub fn new(selection: Selection, output_kind: OutputKind, seed: u64) -> Self {
+        let rng = RandGenerator::new();
+        rng.srand(seed);
+        Self {
+            selection,
+            output_kind,
+            rng,
+        }
+    }
+
+    pub fn selection(&self) -> Selection {
+        self.selection
+    }
+
+    /// Index of the selected action.
+    pub fn select(&self, scores: &[f32]) -> usize {
+        match self.selection {
+            Selection::Greedy => argmax(scores),
+            Selection::EpsilonGreedy { epsilon } => {
+                if self.rng.gen_range(0.0, 1.0) < epsilon {
+                    self.rng.gen_range(0, scores.len())
+                } else {
+                    argmax(scores)
+                }
+            }
+            Selection::Sample { temperature } => self.sample(scores, temperature),
+        }
+    }
+
+    fn sample(&self, scores: &[f32], temperature: f32) -> usize {
+        let weights = tempered_weights(self.output_kind, scores, temperature);
+        let total: f32 = weights.iter().sum();
+
+        let mut threshold = self.rng.gen_range(0.0, total);
+        for (index, weight) in weights.iter().enumerate() {
+            if threshold < *weight {
+                return index;
+            }
+            threshold -= weight;
+        }
+        argmax(scores)
+    }
+}
+
+/// Unnormalized probabilities of the actions scored by `scores` at the given temperature.
+fn tempered_weights(output_kind: OutputKind, scores: &[f32], temperature: f32) -> Vec<f32> {
+    let logits: Vec<f32> = match output_kind {
+        OutputKind::Logits => scores.to_vec(),
+        OutputKind::Probabilities => scores
+            .iter()
+            .map(|&p| p.max(f32::MIN_POSITIVE).ln())
+            .collect(),
+    };
+    // softmax of the tempered logits, shifted by the maximum for numerical stability
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    logits
+        .iter()
+        .map(|&logit| ((logit - max) / temperature).exp())
+        .collect()
+}
+
+fn argmax(scores: &[f32]) -> usize {
+    let mut max_index = 0;
+    let mut max_val = f32::NEG_INFINITY;
+    for (index, &val) in scores.iter().enumerate() {
+        if val > max_val {
+            max_val = val;
+            max_index = index;
+        }
+    }
+    max_index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-5, "{actual:?} != {expected:?}");
+        }
+    }
+
+    fn normalized(weights: Vec<f32>) -> Vec<f32> {
+        let total: f32 = weights.iter().sum();
+        weights.iter().map(|weight| weight / total).collect()
+    }
+
+    #[test]
+    fn parse_selections() {
+        assert_eq!("greedy".parse::<Selection>().unwrap(), Selection::Greedy);
+        assert_eq!(
+            "sample".parse::<Selection>().unwrap(),
+            Selection::Sample { temperature: 1.0 }
+        );
+        assert_eq!(
+            "sample:0.5".parse::<Selection>().unwrap(),
+            Selection::Sample { temperature: 0.5 }
+        );
+        assert_eq!(
+            "epsilon:0.1".parse::<Selection>().unwrap(),
+            Selection::EpsilonGreedy { epsilon: 0.1 }
+        );
+        for invalid in [
+            "",
+            "random",
+            "greedy:1",
+            "sample:",
+            "sample:hot",
+            "sample:0",
+            "sample:-1",
+            "epsilon",
+            "epsilon:-0.1",
+            "epsilon:1.5",
+        ] {
+            assert!(
+                matches!(invalid.parse::<Selection>(), Err(Error::Selection(_))),
+                "{invalid:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn logits_to_probabilities() {
+        let probabilities = OutputKind::Logits.probabilities(&[0.0, 2f32.ln(), 3f32.ln()]);
+        assert_close(&probabilities, &[1.0 / 6.0, 2.0 / 6.0, 3.0 / 6.0]);
+        assert_close(
+            &OutputKind::Probabilities.probabilities(&[0.25, 0.75]),
+            &[0.25, 0.75],
+        );
+    }
+
+    #[test]
+    fn temperature_sharpens_and_flattens() {
+        let scores = [0.2, 0.8];
+        let weights = |temperature| {
+            normalized(tempered_weights(
+                OutputKind::Probabilities,
+                &scores,
+                temperature,
+            ))
+        };
+        assert_close(&weights(1.0), &[0.2, 0.8]);
+        // p^(1/T), renormalized
+        assert_close(&weights(0.5), &[1.0 / 17.0, 16.0 / 17.0]);
+        assert_close(&weights(2.0), &[1.0 / 3.0, 2.0 / 3.0]);
+
+        let logits = normalized(tempered_weights(OutputKind::Logits, &[1.0, 3.0], 2.0));
+        assert_close(&logits, &normalized(vec![0.5f32.exp(), 1.5f32.exp()]));
+    }
+
+    #[test]
+    fn greedy_picks_the_argmax() {
+        let selector = ActionSelector::new(Selection::Greedy, OutputKind::Probabilities, 0);
+        assert_eq!(selector.select(&[0.1, 0.6, 0.3]), 1);
+        assert_eq!(selector.select(&[0.7, 0.2, 0.1]), 0);
+        assert_eq!(selector.select(&[-3.0, -2.0, -1.0]), 2);
+    }
+
+    #[test]
+    fn seeded_sampling_is_reproducible() {
+        let scores = [0.2, 0.3, 0.5];
+        let picks = |seed| {
+            let selection = Selection::Sample { temperature: 1.0 };
+            let selector = ActionSelector::new(selection, OutputKind::Probabilities, seed);
+            (0..100)
+                .map(|_| selector.select(&scores))
+                .collect::<Vec<_>>()
+        };
+        let picks_1 = picks(1);
+        assert_eq!(picks_1, picks(1));
+        assert_ne!(picks_1, picks(2));
+        for action in 0..scores.len() {
+            assert!(picks_1.contains(&action));
+        }
+    }
+
+    #[test]
+    fn low_temperature_picks_the_argmax() {
+        let selection = Selection::Sample { temperature: 0.01 };
+        let selector = ActionSelector::new(selection, OutputKind::Probabilities, 3);
+        for _ in 0..100 {
+            assert_eq!(selector.select(&[0.3, 0.4, 0.3]), 1);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::VecDeque;
+
+/// Memory a policy carries through one episode: the last observations for frame-stacked models
+/// and the recurrent state of recurrent ones.
+///
+/// A fresh state starts an episode, [`OnnxController`](crate::OnnxController) keeps one for
+/// [`Controller::control`](racer_logic::controller::Controller::control) and the
+/// [`BatchEvaluator`](crate::BatchEvaluator) one per environment.
+#[derive(Debug, Clone, Default)]
+pub struct PolicyState {
+    frames: VecDeque<Vec<f32>>,
+    recurrent: Vec<Vec<f32>>,
+}
+
+impl PolicyState {
+    /// Pushes the newest observation and returns the last `frame_stack` ones concatenated,
+    /// oldest first. At the start of an episode the first observation fills the whole stack.
+    pub(crate) fn stack(&mut self, observation: Vec<f32>, frame_stack: usize) -> Vec<f32> {
+        while self.frames.len() < frame_stack.saturating_sub(1) {
+            self.frames.push_back(observation.clone());
+        }
+        self.frames.push_back(observation);
+        while self.frames.len() > frame_stack {
+            self.frames.pop_front();
+        }
+        self.frames.iter().flatten().copied().collect()
+    }
+
+    /// Recurrent state fed to the model, zeros at the start of an episode.
+    pub(crate) fn recurrent(&mut self, sizes: &[usize]) -> &[Vec<f32>] {
+        if self.recurrent.is_empty() {
+            self.recurrent = sizes.iter().map(|&size| vec![0.0; size]).collect();
+        }
+        &self.recurrent
+    }
+
+    pub(crate) fn set_recurrent(&mut self, recurrent: Vec<Vec<f32>>) {
+        self.recurrent = recurrent;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_observation_fills_the_stack() {
+        let mut state = PolicyState::default();
+        assert_eq!(
+            state.stack(vec![1.0, 2.0], 3),
+            [1.0, 2.0, 1.0, 2.0, 1.0, 2.0]
+        );
+        assert_eq!(
+            state.stack(vec![3.0, 4.0], 3),
+            [1.0, 2.0, 1.0, 2.0, 3.0, 4.0]
+        );
+    }
+
+    #[test]
+    fn oldest_frame_is_evicted() {
+        let mut state = PolicyState::default();
+        for frame in 0..5 {
+            state.stack(vec![frame as f32], 3);
+        }
+        assert_eq!(state.stack(vec![5.0], 3), [3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn single_frames_are_not_stacked() {
+        let mut state = PolicyState::default();
+        assert_eq!(state.stack(vec![1.0], 1), [1.0]);
+        assert_eq!(state.stack(vec![2.0], 1), [2.0]);
+        assert!(state.stack(vec![3.0], 0).is_empty());
+    }
+
+    #[test]
+    fn recurrent_state_starts_at_zero() {
+        let mut state = PolicyState::default();
+        assert_eq!(state.recurrent(&[2, 1]), [vec![0.0, 0.0], vec![0.0]]);
+        state.set_recurrent(vec![vec![1.0, 2.0], vec![3.0]]);
+        assert_eq!(state.recurrent(&[2, 1]), [vec![1.0, 2.0], vec![3.0]]);
+    }
+}
@@ -3,6 +3,8 @@ from itertools import count
 from sklearn.preprocessing import MinMaxScaler
 from torch.distributions import Categorical
 from typing import cast
+import json
+import onnx
 import pandas as pd
 import racer_gym
 import torch
@@ -40,17 +42,39 @@ class Policy(nn.Module):
             path,
             input_names=["input"],
             output_names=["output"],
+            # lets racer_headless evaluate many environments with one inference
+            dynamic_axes={"input": {0: "batch_size"}, "output": {0: "batch_size"}},
         )
+        # contract checked by racer_onnx_controller when the model is loaded
+        model = onnx.load(path)
+        onnx.helper.set_model_props(
+            model,
+            {
+                "racer.input_name": "input",
+                "racer.output_name": "output",
+                "racer.observation_version": str(OBSERVATION_VERSION),
+                "racer.observation_features": json.dumps(observation_features[: self.obs_dim]),
+                "racer.action_mapping": json.dumps(list(policy_output_to_action.values())),
+                "racer.policy": "stochastic",
+                "racer.output_kind": "probabilities",
+            },
+        )
+        onnx.save(model, path)
+
+
+# must match `OBSERVATION_VERSION` and `Observation::feature_names` in racer_logic
+OBSERVATION_VERSION = 1
+observation_features = (
+    ["velocity", "steering_angle", "next_wp_angle", "next_wp_dist"]
+    + [f"wheel_on_track_{i}" for i in ("front_r", "front_l", "rear_r", "rear_l")]
+    + [f"sensor_readings_{i}" for i in range(13)]
+)
 
 
 def create_scale_layer(data_path: str, obs_dim: int) -> nn.Linear:
-    col_names = (
-        ["velocity", "steering_angle", "next_wp_angle", "next_wp_dist"]
-        + [f"wheel_on_track_{i}" for i in ("front_r", "front_l", "rear_r", "rear_l")]
-        + [f"sensor_readings_{i}" for i in range(13)]
-        + ["target_steer", "target_throttle"]
-    )
-    data = pd.read_csv(data_path, names=col_names)
+    col_names = observation_features + ["target_steer", "target_throttle"]
+    # recorded with `racer_game --record train.csv`
+    data = pd.read_csv(data_path, usecols=col_names)[col_names]
     scaler = MinMaxScaler(feature_range=(-1, 1), copy=True, clip=False)
     scaler.fit(data)
     min_, max_ = scaler.feature_range
@@ -104,9 +128,10 @@ def main():
     optimizer = optim.Adam(policy.parameters(), lr=1e-3)
 
     running_reward = 10
+    # one line of metrics per episode, see racer_logic::metrics
+    env = racer_gym.Environment(metrics="train_reinforce.jsonl")
     for i_episode in count(1):
-        env = racer_gym.Environment(seed=i_episode)
-        observation = env.observation()
+        observation = env.reset(seed=i_episode)
         ep_reward = 0
         rewards = []
         log_probs = []